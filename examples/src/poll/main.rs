@@ -336,7 +336,11 @@ impl Filesystem for Poll {
 
                 ready.store(true, Ordering::SeqCst);
 
-                notify.wakeup(kh).await;
+                if let Err(err) = notify.wakeup(kh).await {
+                    debug!("notify failed {}", err);
+
+                    return;
+                }
 
                 debug!("notify done");
             });
@@ -0,0 +1,347 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+#[cfg(target_os = "linux")]
+use nix::sys::utsname;
+
+use crate::raw::abi::*;
+#[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
+use crate::raw::connection::FuseConnection;
+
+/// fuse capabilities, they are negotiated with kernel by the `INIT` request.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct FuseCapabilities(u32);
+
+impl FuseCapabilities {
+    /// asynchronous read requests.
+    pub const ASYNC_READ: Self = Self(FUSE_ASYNC_READ);
+    /// locking for POSIX file locks.
+    pub const POSIX_LOCKS: Self = Self(FUSE_POSIX_LOCKS);
+    /// kernel sends file handle for fstat, etc...
+    pub const FILE_OPS: Self = Self(FUSE_FILE_OPS);
+    /// handles the `O_TRUNC` open flag in the filesystem.
+    pub const ATOMIC_O_TRUNC: Self = Self(FUSE_ATOMIC_O_TRUNC);
+    /// filesystem handles lookups of "." and "..".
+    pub const EXPORT_SUPPORT: Self = Self(FUSE_EXPORT_SUPPORT);
+    /// filesystem can handle write size larger than 4kB.
+    pub const BIG_WRITES: Self = Self(FUSE_BIG_WRITES);
+    /// don't apply umask to file mode on create operations.
+    pub const DONT_MASK: Self = Self(FUSE_DONT_MASK);
+    #[cfg(not(target_os = "macos"))]
+    /// kernel supports splice write on the device.
+    pub const SPLICE_WRITE: Self = Self(FUSE_SPLICE_WRITE);
+    #[cfg(not(target_os = "macos"))]
+    /// kernel supports splice move on the device.
+    pub const SPLICE_MOVE: Self = Self(FUSE_SPLICE_MOVE);
+    #[cfg(not(target_os = "macos"))]
+    /// kernel supports splice read on the device.
+    pub const SPLICE_READ: Self = Self(FUSE_SPLICE_READ);
+    /// locking for BSD style file locks.
+    pub const FLOCK_LOCKS: Self = Self(FUSE_FLOCK_LOCKS);
+    /// kernel supports ioctl on directories.
+    pub const HAS_IOCTL_DIR: Self = Self(FUSE_HAS_IOCTL_DIR);
+    /// automatically invalidate cached pages.
+    pub const AUTO_INVAL_DATA: Self = Self(FUSE_AUTO_INVAL_DATA);
+    /// do READDIRPLUS (READDIR+LOOKUP in one).
+    pub const READDIRPLUS: Self = Self(FUSE_DO_READDIRPLUS);
+    /// adaptive readdirplus.
+    pub const READDIRPLUS_AUTO: Self = Self(FUSE_READDIRPLUS_AUTO);
+    /// asynchronous direct I/O submission.
+    pub const ASYNC_DIO: Self = Self(FUSE_ASYNC_DIO);
+    /// use writeback cache for buffered writes.
+    pub const WRITEBACK_CACHE: Self = Self(FUSE_WRITEBACK_CACHE);
+    /// kernel supports zero-message opens.
+    pub const NO_OPEN_SUPPORT: Self = Self(FUSE_NO_OPEN_SUPPORT);
+    /// allow parallel lookups and readdir.
+    pub const PARALLEL_DIROPS: Self = Self(FUSE_PARALLEL_DIROPS);
+    /// fs handles killing suid/sgid/cap on write/chown/trunc.
+    pub const HANDLE_KILLPRIV: Self = Self(FUSE_HANDLE_KILLPRIV);
+    /// filesystem supports posix acls.
+    pub const POSIX_ACL: Self = Self(FUSE_POSIX_ACL);
+    /// reading the device after abort returns `ECONNABORTED`.
+    pub const ABORT_ERROR: Self = Self(FUSE_ABORT_ERROR);
+    /// init_out.max_pages contains the max number of req pages.
+    pub const MAX_PAGES: Self = Self(FUSE_MAX_PAGES);
+    /// cache READLINK responses.
+    pub const CACHE_SYMLINKS: Self = Self(FUSE_CACHE_SYMLINKS);
+    /// kernel supports zero-message opendir.
+    pub const NO_OPENDIR_SUPPORT: Self = Self(FUSE_NO_OPENDIR_SUPPORT);
+    /// only invalidate cached pages on explicit request.
+    pub const EXPLICIT_INVAL_DATA: Self = Self(FUSE_EXPLICIT_INVAL_DATA);
+    /// map_alignment field is valid.
+    pub const MAP_ALIGNMENT: Self = Self(FUSE_MAP_ALIGNMENT);
+
+    const NAMES: &'static [(Self, &'static str)] = &[
+        (Self::ASYNC_READ, "ASYNC_READ"),
+        (Self::POSIX_LOCKS, "POSIX_LOCKS"),
+        (Self::FILE_OPS, "FILE_OPS"),
+        (Self::ATOMIC_O_TRUNC, "ATOMIC_O_TRUNC"),
+        (Self::EXPORT_SUPPORT, "EXPORT_SUPPORT"),
+        (Self::BIG_WRITES, "BIG_WRITES"),
+        (Self::DONT_MASK, "DONT_MASK"),
+        #[cfg(not(target_os = "macos"))]
+        (Self::SPLICE_WRITE, "SPLICE_WRITE"),
+        #[cfg(not(target_os = "macos"))]
+        (Self::SPLICE_MOVE, "SPLICE_MOVE"),
+        #[cfg(not(target_os = "macos"))]
+        (Self::SPLICE_READ, "SPLICE_READ"),
+        (Self::FLOCK_LOCKS, "FLOCK_LOCKS"),
+        (Self::HAS_IOCTL_DIR, "HAS_IOCTL_DIR"),
+        (Self::AUTO_INVAL_DATA, "AUTO_INVAL_DATA"),
+        (Self::READDIRPLUS, "READDIRPLUS"),
+        (Self::READDIRPLUS_AUTO, "READDIRPLUS_AUTO"),
+        (Self::ASYNC_DIO, "ASYNC_DIO"),
+        (Self::WRITEBACK_CACHE, "WRITEBACK_CACHE"),
+        (Self::NO_OPEN_SUPPORT, "NO_OPEN_SUPPORT"),
+        (Self::PARALLEL_DIROPS, "PARALLEL_DIROPS"),
+        (Self::HANDLE_KILLPRIV, "HANDLE_KILLPRIV"),
+        (Self::POSIX_ACL, "POSIX_ACL"),
+        (Self::ABORT_ERROR, "ABORT_ERROR"),
+        (Self::MAX_PAGES, "MAX_PAGES"),
+        (Self::CACHE_SYMLINKS, "CACHE_SYMLINKS"),
+        (Self::NO_OPENDIR_SUPPORT, "NO_OPENDIR_SUPPORT"),
+        (Self::EXPLICIT_INVAL_DATA, "EXPLICIT_INVAL_DATA"),
+        (Self::MAP_ALIGNMENT, "MAP_ALIGNMENT"),
+    ];
+
+    #[cfg(target_os = "linux")]
+    /// the Linux kernel version which starts to offer the capabilities.
+    const KERNEL_VERSIONS: &'static [((u32, u32, u32), Self)] = &[
+        ((2, 6, 14), Self::ASYNC_READ),
+        ((2, 6, 18), Self::POSIX_LOCKS),
+        ((2, 6, 26), Self::FILE_OPS),
+        ((2, 6, 26), Self::ATOMIC_O_TRUNC),
+        ((2, 6, 26), Self::BIG_WRITES),
+        ((2, 6, 29), Self::EXPORT_SUPPORT),
+        ((2, 6, 31), Self::DONT_MASK),
+        ((2, 6, 35), Self::SPLICE_WRITE),
+        ((2, 6, 35), Self::SPLICE_MOVE),
+        ((2, 6, 35), Self::SPLICE_READ),
+        ((3, 1, 0), Self::FLOCK_LOCKS),
+        ((3, 1, 0), Self::HAS_IOCTL_DIR),
+        ((3, 6, 0), Self::AUTO_INVAL_DATA),
+        ((3, 9, 0), Self::READDIRPLUS),
+        ((3, 9, 0), Self::READDIRPLUS_AUTO),
+        ((3, 10, 0), Self::ASYNC_DIO),
+        ((3, 15, 0), Self::WRITEBACK_CACHE),
+        ((4, 5, 0), Self::NO_OPEN_SUPPORT),
+        ((4, 7, 0), Self::PARALLEL_DIROPS),
+        ((4, 9, 0), Self::HANDLE_KILLPRIV),
+        ((4, 9, 0), Self::POSIX_ACL),
+        ((4, 19, 0), Self::ABORT_ERROR),
+        ((4, 20, 0), Self::MAX_PAGES),
+        ((4, 20, 0), Self::CACHE_SYMLINKS),
+        ((5, 1, 0), Self::NO_OPENDIR_SUPPORT),
+        ((5, 2, 0), Self::EXPLICIT_INVAL_DATA),
+        ((5, 10, 0), Self::MAP_ALIGNMENT),
+    ];
+
+    /// no capabilities.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// get the raw init flags of the capabilities.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// create capabilities from raw init flags, unknown flags are kept.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// return true if there is no capability.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// return true if all capabilities in `other` are contained in `self`.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// return the capabilities which are in `self` but not in `other`.
+    pub const fn difference(&self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    #[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
+    /// probe the capabilities the kernel offers without mounting.
+    ///
+    /// # Notes:
+    ///
+    /// the `INIT` negotiation only happens after mount, so the capabilities are derived from the
+    /// running Linux kernel version, it is an approximation, a kernel with backported features
+    /// may offer more capabilities, and the capabilities still need to be enabled by the related
+    /// mount options. On other platforms, `Unsupported` error is returned.
+    pub async fn probe() -> io::Result<Self> {
+        FuseConnection::probe_capabilities().await
+    }
+
+    #[cfg(target_os = "linux")]
+    /// derive the capabilities from the running Linux kernel version.
+    pub(crate) fn from_running_kernel() -> io::Result<Self> {
+        let uts_name = utsname::uname();
+        let release = uts_name.release();
+
+        match parse_kernel_release(release) {
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid kernel release {}", release),
+            )),
+
+            Some(kernel_version) => Ok(Self::from_kernel_version(kernel_version)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    /// the kernel version table is Linux only.
+    pub(crate) fn from_running_kernel() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "probe capabilities is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn from_kernel_version(kernel_version: (u32, u32, u32)) -> Self {
+        Self::KERNEL_VERSIONS
+            .iter()
+            .filter(|(version, _)| *version <= kernel_version)
+            .fold(Self::empty(), |capabilities, (_, capability)| {
+                capabilities | *capability
+            })
+    }
+}
+
+/// parse the `major.minor.patch` version from the Linux kernel release, like `5.10.0-rc1`, a
+/// missing or invalid patch version is treated as 0.
+#[cfg(target_os = "linux")]
+fn parse_kernel_release(release: &str) -> Option<(u32, u32, u32)> {
+    let mut version = release
+        .split(|c: char| !c.is_ascii_digit())
+        .take(3)
+        .map(|num| num.parse::<u32>());
+
+    let (major, minor) = match (version.next(), version.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor),
+
+        _ => return None,
+    };
+
+    let patch = match version.next() {
+        Some(Ok(patch)) => patch,
+        _ => 0,
+    };
+
+    Some((major, minor, patch))
+}
+
+impl BitOr for FuseCapabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for FuseCapabilities {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for FuseCapabilities {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Display for FuseCapabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut rest = *self;
+        let mut names = vec![];
+
+        for (capability, name) in Self::NAMES {
+            if self.contains(*capability) {
+                names.push(name.to_string());
+
+                rest = rest.difference(*capability);
+            }
+        }
+
+        if !rest.is_empty() {
+            names.push(format!("{:#x}", rest.0));
+        }
+
+        if names.is_empty() {
+            return f.write_str("(empty)");
+        }
+
+        f.write_str(&names.join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_release() {
+        assert_eq!(parse_kernel_release("6.18.44-fc-v130"), Some((6, 18, 44)));
+        assert_eq!(parse_kernel_release("5.10-rc1"), Some((5, 10, 0)));
+        assert_eq!(parse_kernel_release("5.15.0-91-generic"), Some((5, 15, 0)));
+        assert_eq!(parse_kernel_release("4.9"), Some((4, 9, 0)));
+        assert_eq!(parse_kernel_release("linux"), None);
+        assert_eq!(parse_kernel_release("5"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kernel_version_boundary() {
+        assert!(!FuseCapabilities::from_kernel_version((3, 14, 79))
+            .contains(FuseCapabilities::WRITEBACK_CACHE));
+        assert!(FuseCapabilities::from_kernel_version((3, 15, 0))
+            .contains(FuseCapabilities::WRITEBACK_CACHE));
+
+        assert!(!FuseCapabilities::from_kernel_version((2, 6, 25))
+            .contains(FuseCapabilities::BIG_WRITES));
+        assert!(FuseCapabilities::from_kernel_version((2, 6, 26))
+            .contains(FuseCapabilities::BIG_WRITES));
+
+        assert!(FuseCapabilities::from_kernel_version((2, 6, 13)).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kernel_version_from_release() {
+        let capabilities =
+            FuseCapabilities::from_kernel_version(parse_kernel_release("5.10-rc1").unwrap());
+
+        assert!(capabilities.contains(
+            FuseCapabilities::MAP_ALIGNMENT
+                | FuseCapabilities::READDIRPLUS
+                | FuseCapabilities::WRITEBACK_CACHE
+        ));
+
+        let capabilities =
+            FuseCapabilities::from_kernel_version(parse_kernel_release("5.4.0").unwrap());
+
+        assert!(!capabilities.contains(FuseCapabilities::MAP_ALIGNMENT));
+        assert!(capabilities.contains(FuseCapabilities::NO_OPENDIR_SUPPORT));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(FuseCapabilities::empty().to_string(), "(empty)");
+        assert_eq!(
+            (FuseCapabilities::READDIRPLUS | FuseCapabilities::WRITEBACK_CACHE).to_string(),
+            "READDIRPLUS | WRITEBACK_CACHE"
+        );
+    }
+}
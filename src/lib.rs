@@ -23,6 +23,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 pub use async_trait::async_trait;
 use nix::sys::stat::mode_t;
 
+pub use capabilities::FuseCapabilities;
 pub use errno::Errno;
 pub use helper::{mode_from_kind_and_perm, perm_from_mode_and_kind};
 pub use mount_options::MountOptions;
@@ -31,6 +32,7 @@ use raw::abi::{
     FATTR_MODE, FATTR_MTIME, FATTR_MTIME_NOW, FATTR_SIZE, FATTR_UID,
 };
 
+mod capabilities;
 mod errno;
 mod helper;
 mod mount_options;
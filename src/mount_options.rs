@@ -1,8 +1,11 @@
 use std::ffi::OsString;
 use std::os::unix::io::RawFd;
+use std::time::Duration;
 
 use nix::unistd;
 
+use crate::FuseCapabilities;
+
 /// mount options.
 #[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub struct MountOptions {
@@ -38,6 +41,12 @@ pub struct MountOptions {
     pub(crate) force_readdir_plus: bool,
 
     pub(crate) custom_options: Option<OsString>,
+
+    // lib self option
+    pub(crate) slow_op_threshold: Option<Duration>,
+
+    // lib self option
+    pub(crate) required_capabilities: FuseCapabilities,
 }
 
 impl MountOptions {
@@ -166,6 +175,68 @@ impl MountOptions {
         self
     }
 
+    /// log a warning for every operation that takes longer than `threshold`, default is disable.
+    ///
+    /// The warning contains the opcode, inode, uid of the request and the time the operation
+    /// takes, it is a lightweight way to find slow operations without a metrics backend.
+    pub fn slow_op_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_op_threshold.replace(threshold);
+
+        self
+    }
+
+    /// set the capabilities the filesystem requires, if kernel doesn't grant all of them after
+    /// the `INIT` negotiation, the mount will fail with an error listing the missing ones,
+    /// default is no requirement.
+    ///
+    /// # Notes:
+    ///
+    /// some capabilities are only granted when the related option or feature is enabled:
+    ///
+    /// - [`POSIX_LOCKS`] requires the `file-lock` feature.
+    /// - [`DONT_MASK`] requires [`dont_mask`].
+    /// - [`WRITEBACK_CACHE`] requires [`write_back`].
+    /// - [`NO_OPEN_SUPPORT`] requires [`no_open_support`].
+    /// - [`NO_OPENDIR_SUPPORT`] requires [`no_open_dir_support`].
+    /// - [`HANDLE_KILLPRIV`] requires [`handle_killpriv`].
+    /// - [`POSIX_ACL`] requires [`default_permissions`].
+    /// - [`READDIRPLUS_AUTO`] conflicts with [`force_readdir_plus`].
+    ///
+    /// [`FLOCK_LOCKS`], [`HAS_IOCTL_DIR`], [`ABORT_ERROR`], [`EXPLICIT_INVAL_DATA`] and
+    /// [`MAP_ALIGNMENT`] are not supported by this library. If any required capability can never
+    /// be granted, the mount fails before mounting.
+    ///
+    /// When kernel doesn't grant a required capability, the filesystem is already mounted, the
+    /// session replies `EPROTO` to the `INIT` request and returns the error, but it doesn't
+    /// unmount the filesystem, the caller must unmount the mount point, for example with
+    /// `fusermount3 -u`.
+    ///
+    /// [`POSIX_LOCKS`]: FuseCapabilities::POSIX_LOCKS
+    /// [`DONT_MASK`]: FuseCapabilities::DONT_MASK
+    /// [`WRITEBACK_CACHE`]: FuseCapabilities::WRITEBACK_CACHE
+    /// [`NO_OPEN_SUPPORT`]: FuseCapabilities::NO_OPEN_SUPPORT
+    /// [`NO_OPENDIR_SUPPORT`]: FuseCapabilities::NO_OPENDIR_SUPPORT
+    /// [`HANDLE_KILLPRIV`]: FuseCapabilities::HANDLE_KILLPRIV
+    /// [`POSIX_ACL`]: FuseCapabilities::POSIX_ACL
+    /// [`READDIRPLUS_AUTO`]: FuseCapabilities::READDIRPLUS_AUTO
+    /// [`FLOCK_LOCKS`]: FuseCapabilities::FLOCK_LOCKS
+    /// [`HAS_IOCTL_DIR`]: FuseCapabilities::HAS_IOCTL_DIR
+    /// [`ABORT_ERROR`]: FuseCapabilities::ABORT_ERROR
+    /// [`EXPLICIT_INVAL_DATA`]: FuseCapabilities::EXPLICIT_INVAL_DATA
+    /// [`MAP_ALIGNMENT`]: FuseCapabilities::MAP_ALIGNMENT
+    /// [`dont_mask`]: MountOptions::dont_mask
+    /// [`write_back`]: MountOptions::write_back
+    /// [`no_open_support`]: MountOptions::no_open_support
+    /// [`no_open_dir_support`]: MountOptions::no_open_dir_support
+    /// [`handle_killpriv`]: MountOptions::handle_killpriv
+    /// [`default_permissions`]: MountOptions::default_permissions
+    /// [`force_readdir_plus`]: MountOptions::force_readdir_plus
+    pub fn require_capabilities(mut self, capabilities: FuseCapabilities) -> Self {
+        self.required_capabilities = capabilities;
+
+        self
+    }
+
     pub(crate) fn build(&mut self, fd: RawFd) -> OsString {
         let mut opts = vec![
             format!("fd={}", fd),
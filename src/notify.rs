@@ -1,7 +1,9 @@
 //! notify kernel.
 
+use std::collections::HashMap;
 use std::ffi::OsString;
 use std::os::unix::ffi::OsStrExt;
+use std::sync::{Arc, Mutex};
 
 use bincode::Options;
 use bytes::{Buf, Bytes};
@@ -17,16 +19,21 @@ use crate::raw::abi::{
     FUSE_NOTIFY_POLL_WAKEUP_OUT_SIZE, FUSE_NOTIFY_RETRIEVE_OUT_SIZE, FUSE_NOTIFY_STORE_OUT_SIZE,
     FUSE_OUT_HEADER_SIZE,
 };
+use crate::{Inode, Result};
 
 #[derive(Debug, Clone)]
 /// notify kernel there are something need to handle.
 pub struct Notify {
     sender: UnboundedSender<Vec<u8>>,
+    poll_handles: PollHandles,
 }
 
 impl Notify {
-    pub(crate) fn new(sender: UnboundedSender<Vec<u8>>) -> Self {
-        Self { sender }
+    pub(crate) fn new(sender: UnboundedSender<Vec<u8>>, poll_handles: PollHandles) -> Self {
+        Self {
+            sender,
+            poll_handles,
+        }
     }
 
     /// notify kernel there are something need to handle. If notify failed, the `kind` will be
@@ -220,8 +227,18 @@ impl Notify {
     }
 
     /// try to notify kernel the IO is ready, kernel can wakeup the waiting program.
-    pub async fn wakeup(mut self, kh: u64) {
-        let _ = self.notify(NotifyKind::Wakeup { kh }).await;
+    ///
+    /// If the poll handle `kh` is stale, which means the file it belongs to is released, no
+    /// wakeup will be sent and `ENOENT` will be returned. If the session is ended, the wakeup
+    /// can't be sent and `ENODEV` will be returned.
+    pub async fn wakeup(mut self, kh: u64) -> Result<()> {
+        if !self.poll_handles.contains(kh) {
+            return Err(libc::ENOENT.into());
+        }
+
+        self.notify(NotifyKind::Wakeup { kh })
+            .await
+            .or(Err(libc::ENODEV.into()))
     }
 
     /// try to notify the cache invalidation about an inode.
@@ -306,3 +323,97 @@ enum NotifyKind {
         size: u32,
     },
 }
+
+#[derive(Debug, Clone, Default)]
+/// the poll handles registered by kernel.
+///
+/// A poll handle is registered when kernel asks to schedule a notify in `poll`, and it is dropped
+/// when the file it belongs to is released, the file is identified by its inode and `fh`.
+pub(crate) struct PollHandles {
+    handles: Arc<Mutex<HashMap<u64, (Inode, u64)>>>,
+}
+
+impl PollHandles {
+    pub(crate) fn register(&self, kh: u64, inode: Inode, fh: u64) {
+        self.handles
+            .lock()
+            .expect("poll handles lock is poisoned")
+            .insert(kh, (inode, fh));
+    }
+
+    pub(crate) fn release(&self, inode: Inode, fh: u64) {
+        self.handles
+            .lock()
+            .expect("poll handles lock is poisoned")
+            .retain(|_, file| *file != (inode, fh));
+    }
+
+    fn contains(&self, kh: u64) -> bool {
+        self.handles
+            .lock()
+            .expect("poll handles lock is poisoned")
+            .contains_key(&kh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_channel::mpsc::unbounded;
+    use futures_util::future::FutureExt;
+
+    use super::*;
+    use crate::Errno;
+
+    #[test]
+    fn wakeup_stale_poll_handle() {
+        let (sender, mut receiver) = unbounded();
+        let poll_handles = PollHandles::default();
+        let notify = Notify::new(sender.clone(), poll_handles.clone());
+
+        poll_handles.register(1, 2, 3);
+        poll_handles.release(2, 3);
+
+        let result = notify.wakeup(1).now_or_never().unwrap();
+
+        assert_eq!(result, Err(Errno::from(libc::ENOENT)));
+        // nothing is sent
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn wakeup_live_poll_handle() {
+        let (sender, mut receiver) = unbounded();
+        let poll_handles = PollHandles::default();
+        let notify = Notify::new(sender.clone(), poll_handles.clone());
+
+        poll_handles.register(1, 2, 3);
+        // release another file doesn't affect this poll handle
+        poll_handles.release(2, 4);
+
+        notify.wakeup(1).now_or_never().unwrap().unwrap();
+
+        let data = receiver.try_recv().unwrap();
+
+        assert_eq!(
+            data.len(),
+            FUSE_OUT_HEADER_SIZE + FUSE_NOTIFY_POLL_WAKEUP_OUT_SIZE
+        );
+        assert_eq!(&data[data.len() - 8..], &1u64.to_le_bytes());
+    }
+
+    #[test]
+    fn wakeup_after_session_end() {
+        let (sender, receiver) = unbounded();
+        let poll_handles = PollHandles::default();
+        let notify = Notify::new(sender, poll_handles.clone());
+
+        poll_handles.register(1, 2, 3);
+
+        // the session is ended, nobody receives the reply any more
+        drop(receiver);
+
+        let result = notify.wakeup(1).now_or_never().unwrap();
+
+        assert_eq!(result, Err(Errno::from(libc::ENODEV)));
+    }
+}
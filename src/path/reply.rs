@@ -67,6 +67,29 @@ impl From<(Inode, FileAttr)> for crate::raw::reply::FileAttr {
     }
 }
 
+impl From<crate::raw::reply::FileAttr> for FileAttr {
+    fn from(attr: crate::raw::reply::FileAttr) -> Self {
+        FileAttr {
+            size: attr.size,
+            blocks: attr.blocks,
+            atime: attr.atime,
+            mtime: attr.mtime,
+            ctime: attr.ctime,
+            #[cfg(target_os = "macos")]
+            crtime: attr.crtime,
+            kind: attr.kind,
+            perm: attr.perm,
+            nlink: attr.nlink,
+            uid: attr.uid,
+            gid: attr.gid,
+            rdev: attr.rdev,
+            #[cfg(target_os = "macos")]
+            flags: attr.flags,
+            blksize: attr.blksize,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 /// entry reply.
 pub struct ReplyEntry {
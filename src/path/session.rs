@@ -1,23 +1,66 @@
+use std::fmt::{self, Debug, Formatter};
 use std::io;
 use std::path::Path;
+use std::sync::Arc;
 
 use crate::path::inode_path_bridge::InodePathBridge;
 use crate::path::path_filesystem::PathFilesystem;
+use crate::path::reply::FileAttr;
 use crate::raw;
+use crate::raw::session::AttrFilter;
+use crate::raw::Request;
 use crate::MountOptions;
 
 #[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
-#[derive(Debug)]
 /// fuse filesystem session, path based.
 pub struct Session {
     mount_options: MountOptions,
+    attr_filter: Option<AttrFilter>,
 }
 
 #[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
 impl Session {
     /// new a fuse filesystem session.
     pub fn new(mount_options: MountOptions) -> Self {
-        Self { mount_options }
+        Self {
+            mount_options,
+            attr_filter: None,
+        }
+    }
+
+    /// set a filter to transform every [`FileAttr`] replied to kernel, see
+    /// [`raw::Session::with_attr_filter`].
+    ///
+    /// The inode and generation of the attributes are allocated for the path by this library, so
+    /// the filter can't change them.
+    pub fn with_attr_filter<F>(mut self, attr_filter: F) -> Self
+    where
+        F: Fn(&mut FileAttr, &Request) + Send + Sync + 'static,
+    {
+        self.attr_filter.replace(Arc::new(
+            move |attr: &mut raw::reply::FileAttr, request: &Request| {
+                let mut path_attr = FileAttr::from(*attr);
+
+                attr_filter(&mut path_attr, request);
+
+                let generation = attr.generation;
+
+                *attr = (attr.ino, path_attr).into();
+                attr.generation = generation;
+            },
+        ));
+
+        self
+    }
+
+    pub(crate) fn raw_session<FS>(self) -> raw::Session<FS> {
+        let mut session = raw::Session::new(self.mount_options);
+
+        if let Some(attr_filter) = self.attr_filter {
+            session.set_attr_filter(attr_filter);
+        }
+
+        session
     }
 
     #[cfg(feature = "unprivileged")]
@@ -30,7 +73,7 @@ impl Session {
     {
         let bridge = InodePathBridge::new(fs);
 
-        raw::Session::new(self.mount_options)
+        self.raw_session()
             .mount_with_unprivileged(bridge, mount_path)
             .await
     }
@@ -42,8 +85,16 @@ impl Session {
     {
         let bridge = InodePathBridge::new(fs);
 
-        raw::Session::new(self.mount_options)
-            .mount(bridge, mount_path)
-            .await
+        self.raw_session().mount(bridge, mount_path).await
+    }
+}
+
+#[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
+impl Debug for Session {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("mount_options", &self.mount_options)
+            .field("attr_filter", &self.attr_filter.is_some())
+            .finish()
     }
 }
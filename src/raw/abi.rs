@@ -75,7 +75,6 @@ pub const FATTR_FLAGS: u32 = 1 << 31;
 /// asynchronous read requests
 pub const FUSE_ASYNC_READ: u32 = 1 << 0;
 
-#[cfg(feature = "file-lock")]
 /// locking for POSIX file locks
 pub const FUSE_POSIX_LOCKS: u32 = 1 << 1;
 
@@ -234,6 +233,7 @@ pub const FUSE_IOCTL_MAX_IOV: u32 = 256;
 pub const FUSE_POLL_SCHEDULE_NOTIFY: u32 = 1 << 0;
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_attr {
     pub ino: u64,
@@ -297,7 +297,7 @@ pub struct fuse_file_lock {
 #[derive(Debug)]
 pub struct UnknownOpcodeError(pub u32);
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
 pub enum fuse_opcode {
     FUSE_LOOKUP = 1,
@@ -494,6 +494,7 @@ impl TryFrom<u32> for fuse_notify_code {
 pub const FUSE_ENTRY_OUT_SIZE: usize = mem::size_of::<fuse_entry_out>();
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_entry_out {
     pub nodeid: u64,
@@ -530,6 +531,7 @@ pub struct fuse_batch_forget_in {
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_getattr_in {
     pub getattr_flags: u32,
@@ -540,6 +542,7 @@ pub struct fuse_getattr_in {
 pub const FUSE_ATTR_OUT_SIZE: usize = mem::size_of::<fuse_attr_out>();
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_attr_out {
     pub attr_valid: u64,
@@ -677,6 +680,7 @@ pub struct fuse_open_out {
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_release_in {
     pub fh: u64,
@@ -723,6 +727,7 @@ pub struct fuse_write_in {
 pub const FUSE_WRITE_OUT_SIZE: usize = mem::size_of::<fuse_write_out>();
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_write_out {
     pub size: u32,
@@ -910,6 +915,7 @@ pub struct fuse_ioctl_out {
 }
 
 #[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_poll_in {
     pub fh: u64,
@@ -921,6 +927,7 @@ pub struct fuse_poll_in {
 pub const FUSE_POLL_OUT_SIZE: usize = mem::size_of::<fuse_poll_out>();
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
 #[allow(non_camel_case_types)]
 pub struct fuse_poll_out {
     pub revents: u32,
@@ -962,7 +969,7 @@ pub struct fuse_in_header {
 
 pub const FUSE_OUT_HEADER_SIZE: usize = mem::size_of::<fuse_out_header>();
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[allow(non_camel_case_types)]
 pub struct fuse_out_header {
     pub len: u32,
@@ -24,7 +24,7 @@ mod tokio_connection {
     use tracing::debug;
 
     use crate::helper::io_error_from_nix_error;
-    use crate::MountOptions;
+    use crate::{FuseCapabilities, MountOptions};
 
     #[derive(Debug)]
     pub struct FuseConnection {
@@ -55,6 +55,24 @@ mod tokio_connection {
             })
         }
 
+        /// probe the capabilities the kernel offers without mounting, see
+        /// [`FuseCapabilities::probe`].
+        pub async fn probe_capabilities() -> io::Result<FuseCapabilities> {
+            const DEV_FUSE: &str = "/dev/fuse";
+
+            // check the platform is supported before touching the fuse device
+            let capabilities = FuseCapabilities::from_running_kernel()?;
+
+            // make sure the fuse device is available
+            tokio::fs::OpenOptions::new()
+                .write(true)
+                .read(true)
+                .open(DEV_FUSE)
+                .await?;
+
+            Ok(capabilities)
+        }
+
         #[cfg(feature = "unprivileged")]
         pub async fn new_with_unprivileged(
             mount_options: MountOptions,
@@ -227,7 +245,7 @@ mod async_std_connection {
     use tracing::debug;
 
     use crate::helper::io_error_from_nix_error;
-    use crate::MountOptions;
+    use crate::{FuseCapabilities, MountOptions};
 
     #[derive(Debug)]
     pub struct FuseConnection {
@@ -254,6 +272,24 @@ mod async_std_connection {
             })
         }
 
+        /// probe the capabilities the kernel offers without mounting, see
+        /// [`FuseCapabilities::probe`].
+        pub async fn probe_capabilities() -> io::Result<FuseCapabilities> {
+            const DEV_FUSE: &str = "/dev/fuse";
+
+            // check the platform is supported before touching the fuse device
+            let capabilities = FuseCapabilities::from_running_kernel()?;
+
+            // make sure the fuse device is available
+            fs::OpenOptions::new()
+                .write(true)
+                .read(true)
+                .open(DEV_FUSE)
+                .await?;
+
+            Ok(capabilities)
+        }
+
         #[cfg(feature = "unprivileged")]
         pub async fn new_with_unprivileged(
             mount_options: MountOptions,
@@ -183,6 +183,13 @@ pub trait Filesystem {
     /// return value of the write system call will reflect the return value of this operation. `fh`
     /// will contain the value set by the open method, or will be undefined if the open method
     /// didn't set any value.
+    ///
+    /// # Notes:
+    ///
+    /// `offset` is always the offset from `fuse_write_in` which is computed by kernel, even if
+    /// the file is opened with `O_APPEND` or the write back cache is enabled, so the filesystem
+    /// should write the data at `offset` exactly and doesn't need to track the append position
+    /// by itself. `fh` can be used to find the backing object which the data should be written to.
     async fn write(
         &self,
         req: Request,
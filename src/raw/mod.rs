@@ -12,7 +12,7 @@ pub use request::Request;
 pub use session::Session;
 
 pub(crate) mod abi;
-mod connection;
+pub(crate) mod connection;
 mod filesystem;
 pub mod reply;
 mod request;
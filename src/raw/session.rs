@@ -9,6 +9,7 @@ use std::os::unix::ffi::OsStringExt;
 use std::os::unix::io::AsRawFd;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[cfg(all(not(feature = "tokio-runtime"), feature = "async-std-runtime"))]
 use async_std::fs::read_dir;
@@ -27,18 +28,21 @@ use tokio_stream::wrappers::ReadDirStream;
 use tracing::{debug, debug_span, error, instrument, warn, Instrument, Span};
 
 use crate::helper::*;
-use crate::notify::Notify;
+use crate::notify::{Notify, PollHandles};
 use crate::raw::abi::*;
 #[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
 use crate::raw::connection::FuseConnection;
 use crate::raw::filesystem::Filesystem;
-use crate::raw::reply::ReplyXAttr;
+use crate::raw::reply::{FileAttr, ReplyXAttr};
 use crate::raw::request::Request;
-use crate::{Errno, SetAttr};
+use crate::{Errno, FuseCapabilities, SetAttr};
 use crate::{Inode, MountOptions};
 
 const ROOT_INODE: Inode = 1;
 
+/// the attribute filter, see [`Session::with_attr_filter`].
+pub(crate) type AttrFilter = Arc<dyn Fn(&mut FileAttr, &Request) + Send + Sync>;
+
 #[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
 /// fuse filesystem session, inode based.
 pub struct Session<FS> {
@@ -47,6 +51,8 @@ pub struct Session<FS> {
     response_sender: UnboundedSender<Vec<u8>>,
     response_receiver: Option<UnboundedReceiver<Vec<u8>>>,
     mount_options: MountOptions,
+    attr_filter: Option<AttrFilter>,
+    poll_handles: PollHandles,
 }
 
 #[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
@@ -61,14 +67,36 @@ impl<FS> Session<FS> {
             response_sender: sender,
             response_receiver: Some(receiver),
             mount_options,
+            attr_filter: None,
+            poll_handles: PollHandles::default(),
         }
     }
 
+    /// set a filter to transform every [`FileAttr`] replied to kernel.
+    ///
+    /// The filter is called on the attributes of `lookup`, `getattr`, `setattr`, `symlink`,
+    /// `mknod`, `mkdir`, `link`, `create` and `readdirplus` replies, after the filesystem
+    /// handler returns and before the reply is encoded, so it can implement policies like
+    /// squashing the owner or masking the permission without touching every handler.
+    pub fn with_attr_filter<F>(mut self, attr_filter: F) -> Self
+    where
+        F: Fn(&mut FileAttr, &Request) + Send + Sync + 'static,
+    {
+        self.attr_filter.replace(Arc::new(attr_filter));
+
+        self
+    }
+
+    /// set the attribute filter which is already built, it is used by the path based session.
+    pub(crate) fn set_attr_filter(&mut self, attr_filter: AttrFilter) {
+        self.attr_filter.replace(attr_filter);
+    }
+
     /// get a [`notify`].
     ///
     /// [`notify`]: Notify
     fn get_notify(&self) -> Notify {
-        Notify::new(self.response_sender.clone())
+        Notify::new(self.response_sender.clone(), self.poll_handles.clone())
     }
 }
 
@@ -99,6 +127,23 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         Ok(())
     }
 
+    /// reject the required capabilities which can never be granted before mount.
+    fn required_capabilities_check(&self) -> IoResult<()> {
+        let unsupported_capabilities = unsupported_capabilities(&self.mount_options);
+
+        if !unsupported_capabilities.is_empty() {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "required capabilities {} can't be granted",
+                    unsupported_capabilities
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
     #[cfg(feature = "unprivileged")]
     /// mount the filesystem without root permission. This function will block until the filesystem
     /// is unmounted.
@@ -109,6 +154,8 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
     ) -> IoResult<()> {
         let mount_path = mount_path.as_ref();
 
+        self.required_capabilities_check()?;
+
         self.mount_empty_check(mount_path).await?;
 
         let fuse_connection =
@@ -129,6 +176,8 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mount_path = mount_path.as_ref();
 
+        self.required_capabilities_check()?;
+
         self.mount_empty_check(mount_path).await?;
 
         let fuse_connection = FuseConnection::new().await?;
@@ -229,26 +278,14 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
     async fn reply_fuse(
         fuse_connection: Arc<FuseConnection>,
-        mut response_receiver: UnboundedReceiver<Vec<u8>>,
+        response_receiver: UnboundedReceiver<Vec<u8>>,
     ) -> IoResult<()> {
-        while let Some(response) = response_receiver.next().await {
-            if let Err(err) = fuse_connection.write(&response).await {
-                if err.kind() == ErrorKind::NotFound {
-                    warn!(
-                        "may reply interrupted fuse request, ignore this error {}",
-                        err
-                    );
-
-                    continue;
-                }
-
-                error!("reply fuse failed {}", err);
-
-                return Err(err);
-            }
-        }
+        write_replies(response_receiver, |response| {
+            let fuse_connection = fuse_connection.clone();
 
-        Ok(())
+            async move { fuse_connection.write(&response).await.map(|_| ()) }
+        })
+        .await
     }
 
     async fn dispatch(&mut self) -> IoResult<()> {
@@ -310,6 +347,17 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
             debug!("receive opcode {}", opcode);
 
+            let slow_op = self
+                .mount_options
+                .slow_op_threshold
+                .map(|threshold| SlowOp {
+                    threshold,
+                    opcode,
+                    inode: in_header.nodeid,
+                    uid: request.uid,
+                    start: Instant::now(),
+                });
+
             // data = &data[FUSE_IN_HEADER_SIZE..in_header.len as usize - FUSE_IN_HEADER_SIZE];
             data = &data[FUSE_IN_HEADER_SIZE..];
             data = &data[..in_header.len as usize - FUSE_IN_HEADER_SIZE];
@@ -318,6 +366,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                 fuse_opcode::FUSE_INIT => {
                     self.handle_init(request, data, &fuse_connection, &fs)
                         .await?;
+
+                    // init is handled in place, without spawning a new task
+                    if let Some(slow_op) = slow_op {
+                        slow_op.check();
+                    }
                 }
 
                 fuse_opcode::FUSE_DESTROY => {
@@ -331,124 +384,153 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                 }
 
                 fuse_opcode::FUSE_LOOKUP => {
-                    self.handle_lookup(request, in_header, data, &fs).await;
+                    self.handle_lookup(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_FORGET => {
-                    if self.handle_forget(request, in_header, data, &fs).await? {
+                    if self
+                        .handle_forget(request, slow_op, in_header, data, &fs)
+                        .await?
+                    {
                         return Ok(());
                     }
                 }
 
                 fuse_opcode::FUSE_GETATTR => {
-                    self.handle_getattr(request, in_header, data, &fs).await;
+                    self.handle_getattr(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_SETATTR => {
-                    self.handle_setattr(request, in_header, data, &fs).await;
+                    self.handle_setattr(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_READLINK => {
-                    self.handle_readlink(request, in_header, &fs).await;
+                    self.handle_readlink(request, slow_op, in_header, &fs).await;
                 }
 
                 fuse_opcode::FUSE_SYMLINK => {
-                    self.handle_symlink(request, in_header, data, &fs).await;
+                    self.handle_symlink(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_MKNOD => {
-                    self.handle_mknod(request, in_header, data, &fs).await;
+                    self.handle_mknod(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_MKDIR => {
-                    self.handle_mkdir(request, in_header, data, &fs).await;
+                    self.handle_mkdir(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_UNLINK => {
-                    self.handle_unlink(request, in_header, data, &fs).await;
+                    self.handle_unlink(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_RMDIR => {
-                    self.handle_rmdir(request, in_header, data, &fs).await;
+                    self.handle_rmdir(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_RENAME => {
-                    self.handle_rename(request, in_header, data, &fs).await;
+                    self.handle_rename(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_LINK => {
-                    self.handle_link(request, in_header, data, &fs).await;
+                    self.handle_link(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_OPEN => {
-                    self.handle_open(request, in_header, data, &fs).await;
+                    self.handle_open(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_READ => {
-                    self.handle_read(request, in_header, data, &fs).await;
+                    self.handle_read(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_WRITE => {
-                    self.handle_write(request, in_header, data, &fs).await;
+                    self.handle_write(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_STATFS => {
-                    self.handle_statfs(request, in_header, &fs).await;
+                    self.handle_statfs(request, slow_op, in_header, &fs).await;
                 }
 
                 fuse_opcode::FUSE_RELEASE => {
-                    self.handle_release(request, in_header, data, &fs).await;
+                    self.handle_release(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_FSYNC => {
-                    self.handle_fsync(request, in_header, data, &fs).await;
+                    self.handle_fsync(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_SETXATTR => {
-                    self.handle_setxattr(request, in_header, data, &fs).await;
+                    self.handle_setxattr(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_GETXATTR => {
-                    self.handle_getxattr(request, in_header, data, &fs).await;
+                    self.handle_getxattr(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_LISTXATTR => {
-                    self.handle_listxattr(request, in_header, data, &fs).await;
+                    self.handle_listxattr(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_REMOVEXATTR => {
-                    self.handle_removexattr(request, in_header, data, &fs).await;
+                    self.handle_removexattr(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_FLUSH => {
-                    self.handle_flush(request, in_header, data, &fs).await;
+                    self.handle_flush(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_OPENDIR => {
-                    self.handle_opendir(request, in_header, data, &fs).await;
+                    self.handle_opendir(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_READDIR => {
-                    self.handle_readdir(request, in_header, data, &fs).await;
+                    self.handle_readdir(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_RELEASEDIR => {
-                    self.handle_releasedir(request, in_header, data, &fs).await;
+                    self.handle_releasedir(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_FSYNCDIR => {
-                    self.handle_fsyncdir(request, in_header, data, &fs).await;
+                    self.handle_fsyncdir(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 #[cfg(feature = "file-lock")]
                 fuse_opcode::FUSE_GETLK => {
-                    self.handle_getlk(request, in_header, data, &fs).await;
+                    self.handle_getlk(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 #[cfg(feature = "file-lock")]
                 fuse_opcode::FUSE_SETLK | fuse_opcode::FUSE_SETLKW => {
                     self.handle_setlk(
                         request,
+                        slow_op,
                         in_header,
                         data,
                         opcode == fuse_opcode::FUSE_SETLKW,
@@ -458,19 +540,22 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                 }
 
                 fuse_opcode::FUSE_ACCESS => {
-                    self.handle_access(request, in_header, data, &fs).await;
+                    self.handle_access(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_CREATE => {
-                    self.handle_create(request, in_header, data, &fs).await;
+                    self.handle_create(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_INTERRUPT => {
-                    self.handle_interrupt(request, data, &fs).await;
+                    self.handle_interrupt(request, slow_op, data, &fs).await;
                 }
 
                 fuse_opcode::FUSE_BMAP => {
-                    self.handle_bmap(request, in_header, data, &fs).await;
+                    self.handle_bmap(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 /*fuse_opcode::FUSE_IOCTL => {
@@ -493,37 +578,42 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                     let fs = fs.clone();
                 }*/
                 fuse_opcode::FUSE_POLL => {
-                    self.handle_poll(request, in_header, data, &fs).await;
+                    self.handle_poll(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_NOTIFY_REPLY => {
-                    self.handle_notify_reply(request, in_header, data, &fs)
+                    self.handle_notify_reply(request, slow_op, in_header, data, &fs)
                         .await;
                 }
 
                 fuse_opcode::FUSE_BATCH_FORGET => {
-                    self.handle_batch_forget(request, in_header, data, &fs)
+                    self.handle_batch_forget(request, slow_op, in_header, data, &fs)
                         .await;
                 }
 
                 fuse_opcode::FUSE_FALLOCATE => {
-                    self.handle_fallocate(request, in_header, data, &fs).await;
+                    self.handle_fallocate(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_READDIRPLUS => {
-                    self.handle_readdirplus(request, in_header, data, &fs).await;
+                    self.handle_readdirplus(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_RENAME2 => {
-                    self.handle_rename2(request, in_header, data, &fs).await;
+                    self.handle_rename2(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_LSEEK => {
-                    self.handle_lseek(request, in_header, data, &fs).await;
+                    self.handle_lseek(request, slow_op, in_header, data, &fs)
+                        .await;
                 }
 
                 fuse_opcode::FUSE_COPY_FILE_RANGE => {
-                    self.handle_copy_file_range(request, in_header, data, &fs)
+                    self.handle_copy_file_range(request, slow_op, in_header, data, &fs)
                         .await;
                 }
 
@@ -554,15 +644,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                     err, request.unique
                 );
 
-                let init_out_header = fuse_out_header {
-                    len: FUSE_OUT_HEADER_SIZE as u32,
-                    error: libc::EINVAL,
-                    unique: request.unique,
-                };
-
-                let init_out_header_data = get_bincode_config()
-                    .serialize(&init_out_header)
-                    .expect("won't happened");
+                let init_out_header_data = error_reply(request, libc::EINVAL.into());
 
                 if let Err(err) = fuse_connection.write(&init_out_header_data).await {
                     error!("write error init out data to /dev/fuse failed {}", err);
@@ -576,165 +658,20 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         debug!("fuse_init {:?}", init_in);
 
-        let mut reply_flags = 0;
-
-        if init_in.flags & FUSE_ASYNC_READ > 0 {
-            debug!("enable FUSE_ASYNC_READ");
-
-            reply_flags |= FUSE_ASYNC_READ;
-        }
-
-        #[cfg(feature = "file-lock")]
-        if init_in.flags & FUSE_POSIX_LOCKS > 0 {
-            debug!("enable FUSE_POSIX_LOCKS");
-
-            reply_flags |= FUSE_POSIX_LOCKS;
-        }
-
-        if init_in.flags & FUSE_FILE_OPS > 0 {
-            debug!("enable FUSE_FILE_OPS");
-
-            reply_flags |= FUSE_FILE_OPS;
-        }
-
-        if init_in.flags & FUSE_ATOMIC_O_TRUNC > 0 {
-            debug!("enable FUSE_ATOMIC_O_TRUNC");
-
-            reply_flags |= FUSE_ATOMIC_O_TRUNC;
-        }
-
-        if init_in.flags & FUSE_EXPORT_SUPPORT > 0 {
-            debug!("enable FUSE_EXPORT_SUPPORT");
-
-            reply_flags |= FUSE_EXPORT_SUPPORT;
-        }
-
-        if init_in.flags & FUSE_BIG_WRITES > 0 {
-            debug!("enable FUSE_BIG_WRITES");
-
-            reply_flags |= FUSE_BIG_WRITES;
-        }
-
-        if init_in.flags & FUSE_DONT_MASK > 0 && self.mount_options.dont_mask {
-            debug!("enable FUSE_DONT_MASK");
-
-            reply_flags |= FUSE_DONT_MASK;
-        }
-
-        #[cfg(not(target_os = "macos"))]
-        if init_in.flags & FUSE_SPLICE_WRITE > 0 {
-            debug!("enable FUSE_SPLICE_WRITE");
-
-            reply_flags |= FUSE_SPLICE_WRITE;
-        }
-
-        #[cfg(not(target_os = "macos"))]
-        if init_in.flags & FUSE_SPLICE_MOVE > 0 {
-            debug!("enable FUSE_SPLICE_MOVE");
-
-            reply_flags |= FUSE_SPLICE_MOVE;
-        }
-
-        #[cfg(not(target_os = "macos"))]
-        if init_in.flags & FUSE_SPLICE_READ > 0 {
-            debug!("enable FUSE_SPLICE_READ");
-
-            reply_flags |= FUSE_SPLICE_READ;
-        }
-
-        // posix lock used, maybe we don't need bsd lock
-        /*if init_in.flags&FUSE_FLOCK_LOCKS>0 {
-            reply_flags |= FUSE_FLOCK_LOCKS;
-        }*/
-
-        /*if init_in.flags & FUSE_HAS_IOCTL_DIR > 0 {
-            debug!("enable FUSE_HAS_IOCTL_DIR");
-
-            reply_flags |= FUSE_HAS_IOCTL_DIR;
-        }*/
-
-        if init_in.flags & FUSE_AUTO_INVAL_DATA > 0 {
-            debug!("enable FUSE_AUTO_INVAL_DATA");
-
-            reply_flags |= FUSE_AUTO_INVAL_DATA;
-        }
-
-        if init_in.flags & FUSE_DO_READDIRPLUS > 0 || self.mount_options.force_readdir_plus {
-            debug!("enable FUSE_DO_READDIRPLUS");
-
-            reply_flags |= FUSE_DO_READDIRPLUS;
-        }
-
-        if init_in.flags & FUSE_READDIRPLUS_AUTO > 0 && !self.mount_options.force_readdir_plus {
-            debug!("enable FUSE_READDIRPLUS_AUTO");
-
-            reply_flags |= FUSE_READDIRPLUS_AUTO;
-        }
-
-        if init_in.flags & FUSE_ASYNC_DIO > 0 {
-            debug!("enable FUSE_ASYNC_DIO");
-
-            reply_flags |= FUSE_ASYNC_DIO;
-        }
-
-        if init_in.flags & FUSE_WRITEBACK_CACHE > 0 && self.mount_options.write_back {
-            debug!("enable FUSE_WRITEBACK_CACHE");
-
-            reply_flags |= FUSE_WRITEBACK_CACHE;
-        }
-
-        if init_in.flags & FUSE_NO_OPEN_SUPPORT > 0 && self.mount_options.no_open_support {
-            debug!("enable FUSE_NO_OPEN_SUPPORT");
-
-            reply_flags |= FUSE_NO_OPEN_SUPPORT;
-        }
-
-        if init_in.flags & FUSE_PARALLEL_DIROPS > 0 {
-            debug!("enable FUSE_PARALLEL_DIROPS");
-
-            reply_flags |= FUSE_PARALLEL_DIROPS;
-        }
-
-        if init_in.flags & FUSE_HANDLE_KILLPRIV > 0 && self.mount_options.handle_killpriv {
-            debug!("enable FUSE_HANDLE_KILLPRIV");
-
-            reply_flags |= FUSE_HANDLE_KILLPRIV;
-        }
-
-        if init_in.flags & FUSE_POSIX_ACL > 0 && self.mount_options.default_permissions {
-            debug!("enable FUSE_POSIX_ACL");
-
-            reply_flags |= FUSE_POSIX_ACL;
-        }
-
-        if init_in.flags & FUSE_MAX_PAGES > 0 {
-            debug!("enable FUSE_MAX_PAGES");
-
-            reply_flags |= FUSE_MAX_PAGES;
-        }
-
-        if init_in.flags & FUSE_CACHE_SYMLINKS > 0 {
-            debug!("enable FUSE_CACHE_SYMLINKS");
-
-            reply_flags |= FUSE_CACHE_SYMLINKS;
-        }
+        let data = match init_reply(request, &init_in, &self.mount_options) {
+            Err((init_out_header_data, err)) => {
+                if let Err(err) = fuse_connection.write(&init_out_header_data).await {
+                    error!("write error init out data to /dev/fuse failed {}", err);
+                }
 
-        if init_in.flags & FUSE_NO_OPENDIR_SUPPORT > 0 && self.mount_options.no_open_dir_support {
-            debug!("enable FUSE_NO_OPENDIR_SUPPORT");
+                return Err(err);
+            }
 
-            reply_flags |= FUSE_NO_OPENDIR_SUPPORT;
-        }
+            Ok(data) => data,
+        };
 
         if let Err(err) = fs.init(request).await {
-            let init_out_header = fuse_out_header {
-                len: FUSE_OUT_HEADER_SIZE as u32,
-                error: err.into(),
-                unique: request.unique,
-            };
-
-            let init_out_header_data = get_bincode_config()
-                .serialize(&init_out_header)
-                .expect("won't happened");
+            let init_out_header_data = error_reply(request, err);
 
             if let Err(err) = fuse_connection.write(&init_out_header_data).await {
                 error!("write error init out data to /dev/fuse failed {}", err);
@@ -743,37 +680,6 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
             return Err(err.into());
         }
 
-        let init_out = fuse_init_out {
-            major: FUSE_KERNEL_VERSION,
-            minor: FUSE_KERNEL_MINOR_VERSION,
-            max_readahead: init_in.max_readahead,
-            flags: reply_flags,
-            max_background: DEFAULT_MAX_BACKGROUND,
-            congestion_threshold: DEFAULT_CONGESTION_THRESHOLD,
-            max_write: MAX_WRITE_SIZE as u32,
-            time_gran: DEFAULT_TIME_GRAN,
-            max_pages: DEFAULT_MAX_PAGES,
-            map_alignment: DEFAULT_MAP_ALIGNMENT,
-            unused: [0; 8],
-        };
-
-        debug!("fuse init out {:?}", init_out);
-
-        let out_header = fuse_out_header {
-            len: (FUSE_OUT_HEADER_SIZE + FUSE_INIT_OUT_SIZE) as u32,
-            error: 0,
-            unique: request.unique,
-        };
-
-        let mut data = Vec::with_capacity(FUSE_OUT_HEADER_SIZE + FUSE_INIT_OUT_SIZE);
-
-        get_bincode_config()
-            .serialize_into(&mut data, &out_header)
-            .expect("won't happened");
-        get_bincode_config()
-            .serialize_into(&mut data, &init_out)
-            .expect("won't happened");
-
         if let Err(err) = fuse_connection.write(&data).await {
             error!("write init out data to /dev/fuse failed {}", err);
 
@@ -785,10 +691,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         Ok(())
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_lookup(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -807,8 +714,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_lookup"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_lookup"), async move {
             debug!(
                 "lookup unique {} name {:?} in parent {}",
                 request.unique, name, in_header.nodeid
@@ -827,7 +735,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                         .expect("won't happened")
                 }
 
-                Ok(entry) => {
+                Ok(mut entry) => {
+                    apply_attr_filter(&attr_filter, &mut entry.attr, &request);
+
                     let entry_out: fuse_entry_out = entry.into();
 
                     debug!("lookup response {:?}", entry_out);
@@ -856,10 +766,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
     }
 
     /// if Ok(true), quit the dispatch
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_forget(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -889,7 +800,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_forget"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_forget"), async move {
             debug!(
                 "forget unique {} inode {} nlookup {}",
                 request.unique, in_header.nodeid, forget_in.nlookup
@@ -902,10 +813,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         Ok(false)
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_getattr(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -927,8 +839,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_getattr"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_getattr"), async move {
             debug!(
                 "getattr unique {} inode {}",
                 request.unique, in_header.nodeid
@@ -956,7 +869,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                         .expect("won't happened")
                 }
 
-                Ok(attr) => {
+                Ok(mut attr) => {
+                    apply_attr_filter(&attr_filter, &mut attr.attr, &request);
+
                     let attr_out = fuse_attr_out {
                         attr_valid: attr.ttl.as_secs(),
                         attr_valid_nsec: attr.ttl.subsec_nanos(),
@@ -987,10 +902,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_setattr(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -1012,8 +928,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_setattr"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_setattr"), async move {
             let set_attr = SetAttr::from(&setattr_in);
 
             let fh = if setattr_in.valid & FATTR_FH > 0 {
@@ -1040,7 +957,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                         .expect("won't happened")
                 }
 
-                Ok(attr) => {
+                Ok(mut attr) => {
+                    apply_attr_filter(&attr_filter, &mut attr.attr, &request);
+
                     let attr_out: fuse_attr_out = attr.into();
 
                     let out_header = fuse_out_header {
@@ -1066,12 +985,18 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, fs))]
-    async fn handle_readlink(&mut self, request: Request, in_header: fuse_in_header, fs: &Arc<FS>) {
+    #[instrument(skip(self, fs, slow_op))]
+    async fn handle_readlink(
+        &mut self,
+        request: Request,
+        slow_op: Option<SlowOp>,
+        in_header: fuse_in_header,
+        fs: &Arc<FS>,
+    ) {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_readlink"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_readlink"), async move {
             debug!(
                 "readlink unique {} inode {}",
                 request.unique, in_header.nodeid
@@ -1115,10 +1040,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_symlink(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -1154,8 +1080,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_symlink"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_symlink"), async move {
             debug!(
                 "symlink unique {} parent {} name {:?} link {:?}",
                 request.unique, in_header.nodeid, name, link_name
@@ -1177,7 +1104,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                         .expect("won't happened")
                 }
 
-                Ok(entry) => {
+                Ok(mut entry) => {
+                    apply_attr_filter(&attr_filter, &mut entry.attr, &request);
+
                     let entry_out: fuse_entry_out = entry.into();
 
                     let out_header = fuse_out_header {
@@ -1203,10 +1132,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_mknod(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -1245,8 +1175,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_mknod"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_mknod"), async move {
             debug!(
                 "mknod unique {} parent {} name {:?} {:?}",
                 request.unique, in_header.nodeid, name, mknod_in
@@ -1266,7 +1197,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                     reply_error_in_place(err, request, resp_sender).await;
                 }
 
-                Ok(entry) => {
+                Ok(mut entry) => {
+                    apply_attr_filter(&attr_filter, &mut entry.attr, &request);
+
                     let entry_out: fuse_entry_out = entry.into();
 
                     let out_header = fuse_out_header {
@@ -1290,10 +1223,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_mkdir(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -1332,8 +1266,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_mkdir"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_mkdir"), async move {
             debug!(
                 "mkdir unique {} parent {} name {:?} {:?}",
                 request.unique, in_header.nodeid, name, mkdir_in
@@ -1353,7 +1288,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                     reply_error_in_place(err, request, resp_sender).await;
                 }
 
-                Ok(entry) => {
+                Ok(mut entry) => {
+                    apply_attr_filter(&attr_filter, &mut entry.attr, &request);
+
                     let entry_out: fuse_entry_out = entry.into();
 
                     let out_header = fuse_out_header {
@@ -1377,10 +1314,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_unlink(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -1403,7 +1341,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_unlink"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_unlink"), async move {
             debug!(
                 "unlink unique {} parent {} name {:?}",
                 request.unique, in_header.nodeid, name
@@ -1429,10 +1367,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_rmdir(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -1455,7 +1394,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_rmdir"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_rmdir"), async move {
             debug!(
                 "rmdir unique {} parent {} name {:?}",
                 request.unique, in_header.nodeid, name
@@ -1481,10 +1420,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_rename(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -1541,7 +1481,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_rename"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_rename"), async move {
             debug!(
                 "rename unique {} parent {} name {:?} new parent {} new name {:?}",
                 request.unique, in_header.nodeid, name, rename_in.newdir, new_name
@@ -1576,10 +1516,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_link(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -1618,8 +1559,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_link"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_link"), async move {
             debug!(
                 "link unique {} inode {} new parent {} new name {:?}",
                 request.unique, link_in.oldnodeid, in_header.nodeid, name
@@ -1633,7 +1575,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                     reply_error_in_place(err, request, resp_sender).await;
                 }
 
-                Ok(entry) => {
+                Ok(mut entry) => {
+                    apply_attr_filter(&attr_filter, &mut entry.attr, &request);
+
                     let entry_out: fuse_entry_out = entry.into();
 
                     let out_header = fuse_out_header {
@@ -1657,10 +1601,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_open(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -1683,7 +1628,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_open"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_open"), async move {
             debug!(
                 "open unique {} inode {} flags {}",
                 request.unique, in_header.nodeid, open_in.flags
@@ -1720,10 +1665,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_read(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -1746,7 +1692,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_read"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_read"), async move {
             debug!(
                 "read unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, read_in
@@ -1795,18 +1741,19 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_write(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
-        mut data: &[u8],
+        data: &[u8],
         fs: &Arc<FS>,
     ) {
-        let write_in = match get_bincode_config().deserialize::<fuse_write_in>(data) {
+        let (write_in, data) = match decode_write_in(data) {
             Err(err) => {
                 error!(
-                    "deserialize fuse_write_in failed {}, request unique {}",
+                    "decode fuse_write_in failed {}, request unique {}",
                     err, request.unique
                 );
 
@@ -1815,25 +1762,13 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                 return;
             }
 
-            Ok(write_in) => write_in,
+            Ok((write_in, data)) => (write_in, data.to_vec()),
         };
 
-        data = &data[FUSE_WRITE_IN_SIZE..];
-
-        if write_in.size as usize != data.len() {
-            error!("fuse_write_in body len is invalid");
-
-            reply_error_in_place(libc::EINVAL.into(), request, &self.response_sender).await;
-
-            return;
-        }
-
-        let data = data.to_vec();
-
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_write"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_write"), async move {
             debug!(
                 "write unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, write_in
@@ -1880,12 +1815,18 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, fs))]
-    async fn handle_statfs(&mut self, request: Request, in_header: fuse_in_header, fs: &Arc<FS>) {
+    #[instrument(skip(self, fs, slow_op))]
+    async fn handle_statfs(
+        &mut self,
+        request: Request,
+        slow_op: Option<SlowOp>,
+        in_header: fuse_in_header,
+        fs: &Arc<FS>,
+    ) {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_statfs"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_statfs"), async move {
             debug!(
                 "statfs unique {} inode {}",
                 request.unique, in_header.nodeid
@@ -1922,10 +1863,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_release(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -1945,10 +1887,13 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
             Ok(release_in) => release_in,
         };
 
+        // the poll handles of the released file are stale now
+        self.poll_handles.release(in_header.nodeid, release_in.fh);
+
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_release"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_release"), async move {
             let flush = release_in.release_flags & FUSE_RELEASE_FLUSH > 0;
 
             debug!(
@@ -1991,10 +1936,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_fsync(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2017,7 +1963,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_fsync"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_fsync"), async move {
             let data_sync = fsync_in.fsync_flags & 1 > 0;
 
             debug!(
@@ -2048,10 +1994,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_setxattr(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -2119,7 +2066,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_setxattr"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_setxattr"), async move {
             debug!(
                 "setxattr unique {} inode {}",
                 request.unique, in_header.nodeid
@@ -2156,10 +2103,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_getxattr(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -2196,7 +2144,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_getxattr"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_getxattr"), async move {
             debug!(
                 "getxattr unique {} inode {}",
                 request.unique, in_header.nodeid
@@ -2262,10 +2210,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_listxattr(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2288,7 +2237,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_listxattr"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_listxattr"), async move {
             debug!(
                 "listxattr unique {} inode {} size {}",
                 request.unique, in_header.nodeid, listxattr_in.size
@@ -2354,10 +2303,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_removexattr(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2380,7 +2330,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_removexattr"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_removexattr"), async move {
             debug!(
                 "removexattr unique {} inode {}",
                 request.unique, in_header.nodeid
@@ -2407,10 +2357,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_flush(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2433,7 +2384,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_flush"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_flush"), async move {
             debug!(
                 "flush unique {} inode {} fh {} lock_owner {}",
                 request.unique, in_header.nodeid, flush_in.fh, flush_in.lock_owner
@@ -2462,10 +2413,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_opendir(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2488,7 +2440,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_opendir"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_opendir"), async move {
             debug!(
                 "opendir unique {} inode {} flags {}",
                 request.unique, in_header.nodeid, open_in.flags
@@ -2525,10 +2477,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_readdir(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2557,7 +2510,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_readdir"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_readdir"), async move {
             debug!(
                 "readdir unique {} inode {} fh {} offset {}",
                 request.unique, in_header.nodeid, read_in.fh, read_in.offset
@@ -2645,10 +2598,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_releasedir(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2671,7 +2625,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_releasedir"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_releasedir"), async move {
             debug!(
                 "releasedir unique {} inode {} fh {} flags {}",
                 request.unique, in_header.nodeid, release_in.fh, release_in.flags
@@ -2700,10 +2654,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_fsyncdir(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2726,7 +2681,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_fsyncdir"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_fsyncdir"), async move {
             let data_sync = fsync_in.fsync_flags & 1 > 0;
 
             debug!(
@@ -2758,10 +2713,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
     }
 
     #[cfg(feature = "file-lock")]
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_getlk(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2784,7 +2740,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_getlk"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_getlk"), async move {
             debug!(
                 "getlk unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, getlk_in
@@ -2834,10 +2790,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
     }
 
     #[cfg(feature = "file-lock")]
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_setlk(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         block: bool,
@@ -2867,7 +2824,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_setlk"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_setlk"), async move {
             debug!(
                 "setlk unique {} inode {} block {} {:?}",
                 request.unique, in_header.nodeid, block, setlk_in
@@ -2906,10 +2863,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_access(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -2932,7 +2890,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_access"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_access"), async move {
             debug!(
                 "access unique {} inode {} mask {}",
                 request.unique, in_header.nodeid, access_in.mask
@@ -2961,10 +2919,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_create(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -3003,14 +2962,15 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_create"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_create"), async move {
             debug!(
                 "create unique {} parent {} name {:?} mode {} flags {}",
                 request.unique, in_header.nodeid, name, create_in.mode, create_in.flags
             );
 
-            let created = match fs
+            let mut created = match fs
                 .create(
                     request,
                     in_header.nodeid,
@@ -3029,6 +2989,8 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                 Ok(created) => created,
             };
 
+            apply_attr_filter(&attr_filter, &mut created.attr, &request);
+
             let (entry_out, open_out): (fuse_entry_out, fuse_open_out) = created.into();
 
             let out_header = fuse_out_header {
@@ -3054,8 +3016,14 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
-    async fn handle_interrupt(&mut self, request: Request, data: &[u8], fs: &Arc<FS>) {
+    #[instrument(skip(self, data, fs, slow_op))]
+    async fn handle_interrupt(
+        &mut self,
+        request: Request,
+        slow_op: Option<SlowOp>,
+        data: &[u8],
+        fs: &Arc<FS>,
+    ) {
         let interrupt_in = match get_bincode_config().deserialize::<fuse_interrupt_in>(data) {
             Err(err) => {
                 error!(
@@ -3074,7 +3042,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_interrupt"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_interrupt"), async move {
             debug!(
                 "interrupt_in unique {} interrupt unique {}",
                 request.unique, interrupt_in.unique
@@ -3100,10 +3068,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_bmap(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -3126,7 +3095,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_bmap"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_bmap"), async move {
             debug!(
                 "bmap unique {} inode {} block size {} idx {}",
                 request.unique, in_header.nodeid, bmap_in.blocksize, bmap_in.block
@@ -3166,10 +3135,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_poll(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -3189,12 +3159,17 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
             Ok(poll_in) => poll_in,
         };
 
+        if poll_in.flags & FUSE_POLL_SCHEDULE_NOTIFY > 0 {
+            self.poll_handles
+                .register(poll_in.kh, in_header.nodeid, poll_in.fh);
+        }
+
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
         let notify = self.get_notify();
 
-        spawn(debug_span!("fuse_poll"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_poll"), async move {
             debug!(
                 "poll unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, poll_in
@@ -3248,10 +3223,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_notify_reply(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -3289,7 +3265,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_notify_reply"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_notify_reply"), async move {
             if let Err(err) = fs
                 .notify_reply(
                     request,
@@ -3304,10 +3280,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_batch_forget(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         _in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -3359,7 +3336,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_batch_forget"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_batch_forget"), async move {
             let inodes = forgets
                 .into_iter()
                 .map(|forget_one| forget_one.nodeid)
@@ -3371,10 +3348,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_fallocate(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -3397,7 +3375,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_fallocate"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_fallocate"), async move {
             debug!(
                 "fallocate unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, fallocate_in
@@ -3433,10 +3411,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_readdirplus(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -3458,8 +3437,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
+        let attr_filter = self.attr_filter.clone();
 
-        spawn(debug_span!("fuse_readdirplus"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_readdirplus"), async move {
             debug!(
                 "readdirplus unique {} parent {} {:?}",
                 request.unique, in_header.nodeid, readdirplus_in
@@ -3515,7 +3495,9 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
                     break;
                 }
 
-                let attr = entry.attr;
+                let mut attr = entry.attr;
+
+                apply_attr_filter(&attr_filter, &mut attr, &request);
 
                 let dir_entry = fuse_direntplus {
                     entry_out: fuse_entry_out {
@@ -3566,10 +3548,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_rename2(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         mut data: &[u8],
         fs: &Arc<FS>,
@@ -3626,7 +3609,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         let mut resp_sender = self.response_sender.clone();
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_rename2"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_rename2"), async move {
             debug!(
                 "rename2 unique {} parent {} name {:?} new parent {} new name {:?} flags {}",
                 request.unique,
@@ -3667,10 +3650,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_lseek(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -3694,7 +3678,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_lseek"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_lseek"), async move {
             debug!(
                 "lseek unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, lseek_in
@@ -3740,10 +3724,11 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
         });
     }
 
-    #[instrument(skip(self, data, fs))]
+    #[instrument(skip(self, data, fs, slow_op))]
     async fn handle_copy_file_range(
         &mut self,
         request: Request,
+        slow_op: Option<SlowOp>,
         in_header: fuse_in_header,
         data: &[u8],
         fs: &Arc<FS>,
@@ -3768,7 +3753,7 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
 
         let fs = fs.clone();
 
-        spawn(debug_span!("fuse_copy_file_range"), async move {
+        spawn_timed(slow_op, debug_span!("fuse_copy_file_range"), async move {
             debug!(
                 "reply_copy_file_range unique {} inode {} {:?}",
                 request.unique, in_header.nodeid, copy_file_range_in
@@ -3819,36 +3804,1051 @@ impl<FS: Filesystem + Send + Sync + 'static> Session<FS> {
     }
 }
 
-async fn reply_error_in_place<S>(err: Errno, request: Request, sender: S)
-where
-    S: Sink<Vec<u8>>,
-{
-    let out_header = fuse_out_header {
-        len: FUSE_OUT_HEADER_SIZE as u32,
-        error: err.into(),
-        unique: request.unique,
-    };
+/// negotiate the `INIT` reply flags with the flags kernel offers and the mount options.
+fn init_reply_flags(kernel_flags: u32, mount_options: &MountOptions) -> u32 {
+    let mut reply_flags = 0;
 
-    let data = get_bincode_config()
-        .serialize(&out_header)
-        .expect("won't happened");
+    if kernel_flags & FUSE_ASYNC_READ > 0 {
+        reply_flags |= FUSE_ASYNC_READ;
+    }
 
-    futures_util::pin_mut!(sender);
+    #[cfg(feature = "file-lock")]
+    if kernel_flags & FUSE_POSIX_LOCKS > 0 {
+        reply_flags |= FUSE_POSIX_LOCKS;
+    }
 
-    let _ = sender.send(data).await;
-}
+    if kernel_flags & FUSE_FILE_OPS > 0 {
+        reply_flags |= FUSE_FILE_OPS;
+    }
 
-#[inline]
-fn spawn<F>(span: Span, fut: F)
-where
-    F: Future + Send + 'static,
-    F::Output: Send + 'static,
-{
-    #[cfg(all(not(feature = "tokio-runtime"), feature = "async-std-runtime"))]
-    use async_std::task::spawn;
+    if kernel_flags & FUSE_ATOMIC_O_TRUNC > 0 {
+        reply_flags |= FUSE_ATOMIC_O_TRUNC;
+    }
+
+    if kernel_flags & FUSE_EXPORT_SUPPORT > 0 {
+        reply_flags |= FUSE_EXPORT_SUPPORT;
+    }
+
+    if kernel_flags & FUSE_BIG_WRITES > 0 {
+        reply_flags |= FUSE_BIG_WRITES;
+    }
+
+    if kernel_flags & FUSE_DONT_MASK > 0 && mount_options.dont_mask {
+        reply_flags |= FUSE_DONT_MASK;
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    if kernel_flags & FUSE_SPLICE_WRITE > 0 {
+        reply_flags |= FUSE_SPLICE_WRITE;
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    if kernel_flags & FUSE_SPLICE_MOVE > 0 {
+        reply_flags |= FUSE_SPLICE_MOVE;
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    if kernel_flags & FUSE_SPLICE_READ > 0 {
+        reply_flags |= FUSE_SPLICE_READ;
+    }
+
+    // posix lock used, maybe we don't need bsd lock
+    /*if kernel_flags&FUSE_FLOCK_LOCKS>0 {
+        reply_flags |= FUSE_FLOCK_LOCKS;
+    }*/
+
+    /*if kernel_flags & FUSE_HAS_IOCTL_DIR > 0 {
+        reply_flags |= FUSE_HAS_IOCTL_DIR;
+    }*/
+
+    if kernel_flags & FUSE_AUTO_INVAL_DATA > 0 {
+        reply_flags |= FUSE_AUTO_INVAL_DATA;
+    }
+
+    if kernel_flags & FUSE_DO_READDIRPLUS > 0 || mount_options.force_readdir_plus {
+        reply_flags |= FUSE_DO_READDIRPLUS;
+    }
+
+    if kernel_flags & FUSE_READDIRPLUS_AUTO > 0 && !mount_options.force_readdir_plus {
+        reply_flags |= FUSE_READDIRPLUS_AUTO;
+    }
+
+    if kernel_flags & FUSE_ASYNC_DIO > 0 {
+        reply_flags |= FUSE_ASYNC_DIO;
+    }
+
+    if kernel_flags & FUSE_WRITEBACK_CACHE > 0 && mount_options.write_back {
+        reply_flags |= FUSE_WRITEBACK_CACHE;
+    }
+
+    if kernel_flags & FUSE_NO_OPEN_SUPPORT > 0 && mount_options.no_open_support {
+        reply_flags |= FUSE_NO_OPEN_SUPPORT;
+    }
+
+    if kernel_flags & FUSE_PARALLEL_DIROPS > 0 {
+        reply_flags |= FUSE_PARALLEL_DIROPS;
+    }
+
+    if kernel_flags & FUSE_HANDLE_KILLPRIV > 0 && mount_options.handle_killpriv {
+        reply_flags |= FUSE_HANDLE_KILLPRIV;
+    }
+
+    if kernel_flags & FUSE_POSIX_ACL > 0 && mount_options.default_permissions {
+        reply_flags |= FUSE_POSIX_ACL;
+    }
+
+    if kernel_flags & FUSE_MAX_PAGES > 0 {
+        reply_flags |= FUSE_MAX_PAGES;
+    }
+
+    if kernel_flags & FUSE_CACHE_SYMLINKS > 0 {
+        reply_flags |= FUSE_CACHE_SYMLINKS;
+    }
+
+    if kernel_flags & FUSE_NO_OPENDIR_SUPPORT > 0 && mount_options.no_open_dir_support {
+        reply_flags |= FUSE_NO_OPENDIR_SUPPORT;
+    }
+
+    reply_flags
+}
+
+/// build the `INIT` reply for the flags kernel offers. If kernel doesn't grant the required
+/// capabilities, return the `EPROTO` error reply and the mount error instead.
+fn init_reply(
+    request: Request,
+    init_in: &fuse_init_in,
+    mount_options: &MountOptions,
+) -> Result<Vec<u8>, (Vec<u8>, IoError)> {
+    let reply_flags = init_reply_flags(init_in.flags, mount_options);
+
+    debug!(
+        "enable init flags {}",
+        FuseCapabilities::from_bits(reply_flags)
+    );
+
+    let missing_capabilities = missing_capabilities(init_in.flags, mount_options);
+
+    if !missing_capabilities.is_empty() {
+        error!(
+            "kernel doesn't grant required capabilities {}",
+            missing_capabilities
+        );
+
+        return Err((
+            error_reply(request, libc::EPROTO.into()),
+            IoError::other(format!(
+                "kernel doesn't grant required capabilities {}",
+                missing_capabilities
+            )),
+        ));
+    }
+
+    let init_out = fuse_init_out {
+        major: FUSE_KERNEL_VERSION,
+        minor: FUSE_KERNEL_MINOR_VERSION,
+        max_readahead: init_in.max_readahead,
+        flags: reply_flags,
+        max_background: DEFAULT_MAX_BACKGROUND,
+        congestion_threshold: DEFAULT_CONGESTION_THRESHOLD,
+        max_write: MAX_WRITE_SIZE as u32,
+        time_gran: DEFAULT_TIME_GRAN,
+        max_pages: DEFAULT_MAX_PAGES,
+        map_alignment: DEFAULT_MAP_ALIGNMENT,
+        unused: [0; 8],
+    };
+
+    debug!("fuse init out {:?}", init_out);
+
+    let out_header = fuse_out_header {
+        len: (FUSE_OUT_HEADER_SIZE + FUSE_INIT_OUT_SIZE) as u32,
+        error: 0,
+        unique: request.unique,
+    };
+
+    let mut data = Vec::with_capacity(FUSE_OUT_HEADER_SIZE + FUSE_INIT_OUT_SIZE);
+
+    get_bincode_config()
+        .serialize_into(&mut data, &out_header)
+        .expect("won't happened");
+    get_bincode_config()
+        .serialize_into(&mut data, &init_out)
+        .expect("won't happened");
+
+    Ok(data)
+}
+
+/// return the required capabilities which are not granted, a capability is granted only when
+/// kernel offers it and it is enabled in the reply flags.
+fn missing_capabilities(kernel_flags: u32, mount_options: &MountOptions) -> FuseCapabilities {
+    let granted = kernel_flags & init_reply_flags(kernel_flags, mount_options);
+
+    mount_options
+        .required_capabilities
+        .difference(FuseCapabilities::from_bits(granted))
+}
+
+/// check the required capabilities before mount, return the capabilities which can never be
+/// granted even if kernel offers all capabilities, because the related mount option or feature
+/// is not enabled, or this library doesn't support them.
+pub(crate) fn unsupported_capabilities(mount_options: &MountOptions) -> FuseCapabilities {
+    missing_capabilities(u32::MAX, mount_options)
+}
+
+/// decode the `FUSE_WRITE` body into the `fuse_write_in` and the data to write. The `offset` and
+/// `fh` the kernel sends are kept unmodified, the offset is authoritative even for append write.
+fn decode_write_in(data: &[u8]) -> IoResult<(fuse_write_in, &[u8])> {
+    let write_in = get_bincode_config()
+        .deserialize::<fuse_write_in>(data)
+        .map_err(|err| IoError::new(ErrorKind::InvalidData, err))?;
+
+    let data = &data[FUSE_WRITE_IN_SIZE..];
+
+    if write_in.size as usize != data.len() {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            "fuse_write_in body len is invalid",
+        ));
+    }
+
+    Ok((write_in, data))
+}
+
+/// write every reply from `response_receiver` with `write` until the channel is closed.
+///
+/// `ENOENT` means the kernel can't find the reply unique because the request is interrupted, it
+/// doesn't wait for the reply any more, this is a normal race so the reply is dropped. `EINVAL`
+/// means the reply is malformed, it is dropped too but logged as a warning because it is a bug.
+/// Any other error, including `ENODEV` when the session is ending, stops the session.
+async fn write_replies<W, Fut>(
+    mut response_receiver: UnboundedReceiver<Vec<u8>>,
+    mut write: W,
+) -> IoResult<()>
+where
+    W: FnMut(Vec<u8>) -> Fut,
+    Fut: Future<Output = IoResult<()>>,
+{
+    while let Some(response) = response_receiver.next().await {
+        let unique = reply_unique(&response);
+
+        if let Err(err) = write(response).await {
+            match err.raw_os_error() {
+                Some(libc::ENOENT) => {
+                    debug!(
+                        "may reply interrupted fuse request {:?}, drop the reply",
+                        unique
+                    );
+                }
+
+                Some(libc::EINVAL) => {
+                    warn!(
+                        "reply fuse request {:?} is invalid, drop the reply, error {}",
+                        unique, err
+                    );
+                }
+
+                _ => {
+                    error!("reply fuse request {:?} failed {}", unique, err);
+
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// get the request unique from a reply, it is only used for logging.
+fn reply_unique(response: &[u8]) -> Option<u64> {
+    get_bincode_config()
+        .deserialize::<fuse_out_header>(response)
+        .ok()
+        .map(|out_header| out_header.unique)
+}
+
+#[inline]
+fn apply_attr_filter(attr_filter: &Option<AttrFilter>, attr: &mut FileAttr, request: &Request) {
+    if let Some(attr_filter) = attr_filter {
+        attr_filter(attr, request);
+    }
+}
+
+/// the slow operation log context.
+#[derive(Debug, Copy, Clone)]
+struct SlowOp {
+    threshold: Duration,
+    opcode: fuse_opcode,
+    inode: Inode,
+    uid: u32,
+    start: Instant,
+}
+
+impl SlowOp {
+    /// log a warning if the operation takes longer than the threshold, return true if it does.
+    fn check(&self) -> bool {
+        self.check_elapsed(self.start.elapsed())
+    }
+
+    fn check_elapsed(&self, elapsed: Duration) -> bool {
+        if elapsed <= self.threshold {
+            return false;
+        }
+
+        warn!(
+            "slow operation opcode {} inode {} uid {} takes {:?}, threshold {:?}",
+            self.opcode, self.inode, self.uid, elapsed, self.threshold
+        );
+
+        true
+    }
+}
+
+/// spawn the operation task, if the slow operation log is enabled, `slow_op` is built by
+/// `dispatch` and the task will check its duration when done.
+///
+/// All operation tasks must be spawned by this function, otherwise the slow operation log will be
+/// lost.
+#[cfg(any(feature = "async-std-runtime", feature = "tokio-runtime"))]
+fn spawn_timed<F>(slow_op: Option<SlowOp>, span: Span, fut: F)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(not(feature = "tokio-runtime"), feature = "async-std-runtime"))]
+    use async_std::task::spawn;
 
     #[cfg(all(not(feature = "async-std-runtime"), feature = "tokio-runtime"))]
     use tokio::spawn;
 
-    spawn(fut.instrument(span));
+    spawn(
+        async move {
+            let output = fut.await;
+
+            if let Some(slow_op) = slow_op {
+                slow_op.check();
+            }
+
+            output
+        }
+        .instrument(span),
+    );
+}
+
+/// build the reply which only has the out header, the kernel requires the error is a negative
+/// errno, it is negated by the [`Errno`] conversion.
+fn error_reply(request: Request, err: Errno) -> Vec<u8> {
+    let out_header = fuse_out_header {
+        len: FUSE_OUT_HEADER_SIZE as u32,
+        error: err.into(),
+        unique: request.unique,
+    };
+
+    get_bincode_config()
+        .serialize(&out_header)
+        .expect("won't happened")
+}
+
+async fn reply_error_in_place<S>(err: Errno, request: Request, sender: S)
+where
+    S: Sink<Vec<u8>>,
+{
+    let data = error_reply(request, err);
+
+    futures_util::pin_mut!(sender);
+
+    let _ = sender.send(data).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_body(fh: u64, offset: u64, flags: u32, data: &[u8]) -> Vec<u8> {
+        let mut body = Vec::with_capacity(FUSE_WRITE_IN_SIZE + data.len());
+
+        body.extend_from_slice(&fh.to_le_bytes());
+        body.extend_from_slice(&offset.to_le_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes()); // write_flags
+        body.extend_from_slice(&0u64.to_le_bytes()); // lock_owner
+        body.extend_from_slice(&flags.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes()); // padding
+        body.extend_from_slice(data);
+
+        body
+    }
+
+    #[test]
+    fn write_append_offset_unmodified() {
+        let flags = (libc::O_WRONLY | libc::O_APPEND) as u32;
+        let body = write_body(42, 4096, flags, b"hello");
+
+        let (write_in, data) = decode_write_in(&body).unwrap();
+
+        assert_eq!(write_in.fh, 42);
+        assert_eq!(write_in.offset, 4096);
+        assert_eq!(write_in.flags, flags);
+        assert_eq!(data, b"hello");
+    }
+
+    #[test]
+    fn write_invalid_body_len() {
+        let mut body = write_body(42, 4096, 0, b"hello");
+        body.pop();
+
+        assert!(decode_write_in(&body).is_err());
+    }
+
+    #[test]
+    fn kernel_withholds_required_capability() {
+        let mount_options = MountOptions::default()
+            .write_back(true)
+            .require_capabilities(
+                FuseCapabilities::READDIRPLUS | FuseCapabilities::WRITEBACK_CACHE,
+            );
+
+        let kernel_flags = FUSE_ASYNC_READ | FUSE_DO_READDIRPLUS;
+
+        assert_eq!(
+            missing_capabilities(kernel_flags, &mount_options),
+            FuseCapabilities::WRITEBACK_CACHE
+        );
+        assert!(
+            missing_capabilities(kernel_flags | FUSE_WRITEBACK_CACHE, &mount_options).is_empty()
+        );
+
+        let request = Request {
+            unique: 1,
+            uid: 0,
+            gid: 0,
+            pid: 0,
+        };
+
+        let mut init_in = fuse_init_in {
+            major: FUSE_KERNEL_VERSION,
+            minor: FUSE_KERNEL_MINOR_VERSION,
+            max_readahead: 0,
+            flags: kernel_flags,
+        };
+
+        let (reply, err) = init_reply(request, &init_in, &mount_options).unwrap_err();
+
+        // kernel rejects a positive error with EINVAL, it must be a negative errno
+        let out_header = get_bincode_config()
+            .deserialize::<fuse_out_header>(&reply)
+            .unwrap();
+
+        assert_eq!(reply.len(), FUSE_OUT_HEADER_SIZE);
+        assert_eq!(out_header.len as usize, FUSE_OUT_HEADER_SIZE);
+        assert_eq!(out_header.error, -libc::EPROTO);
+        assert_eq!(out_header.unique, 1);
+        assert!(err.to_string().contains("WRITEBACK_CACHE"));
+
+        init_in.flags |= FUSE_WRITEBACK_CACHE;
+
+        let reply = init_reply(request, &init_in, &mount_options).unwrap();
+
+        let out_header = get_bincode_config()
+            .deserialize::<fuse_out_header>(&reply)
+            .unwrap();
+
+        assert_eq!(reply.len(), FUSE_OUT_HEADER_SIZE + FUSE_INIT_OUT_SIZE);
+        assert_eq!(out_header.error, 0);
+    }
+
+    #[test]
+    fn force_readdir_plus_doesnt_grant_readdirplus() {
+        let mount_options = MountOptions::default()
+            .force_readdir_plus(true)
+            .require_capabilities(FuseCapabilities::READDIRPLUS);
+
+        assert_eq!(
+            missing_capabilities(FUSE_ASYNC_READ, &mount_options),
+            FuseCapabilities::READDIRPLUS
+        );
+    }
+
+    #[test]
+    fn required_capability_can_never_be_granted() {
+        let mount_options = MountOptions::default().require_capabilities(
+            FuseCapabilities::WRITEBACK_CACHE | FuseCapabilities::FLOCK_LOCKS,
+        );
+
+        assert_eq!(
+            unsupported_capabilities(&mount_options),
+            FuseCapabilities::WRITEBACK_CACHE | FuseCapabilities::FLOCK_LOCKS
+        );
+
+        let mount_options = mount_options
+            .write_back(true)
+            .require_capabilities(FuseCapabilities::WRITEBACK_CACHE);
+
+        assert!(unsupported_capabilities(&mount_options).is_empty());
+    }
+
+    fn file_attr(ino: Inode) -> FileAttr {
+        FileAttr {
+            ino,
+            generation: 0,
+            size: 0,
+            blocks: 0,
+            atime: std::time::UNIX_EPOCH,
+            mtime: std::time::UNIX_EPOCH,
+            ctime: std::time::UNIX_EPOCH,
+            #[cfg(target_os = "macos")]
+            crtime: std::time::UNIX_EPOCH,
+            kind: crate::FileType::RegularFile,
+            perm: 0o644,
+            nlink: 1,
+            uid: 1000,
+            gid: 1000,
+            rdev: 0,
+            #[cfg(target_os = "macos")]
+            flags: 0,
+            blksize: 4096,
+        }
+    }
+
+    #[test]
+    fn attr_filter_squash_uid() {
+        let attr_filter: Option<AttrFilter> = Some(Arc::new(|attr, request| {
+            if request.uid != 0 {
+                attr.uid = 65534;
+                attr.gid = 65534;
+            }
+        }));
+
+        let request = Request {
+            unique: 1,
+            uid: 1000,
+            gid: 1000,
+            pid: 1,
+        };
+
+        // lookup reply
+        let mut entry = crate::raw::reply::ReplyEntry {
+            ttl: Duration::from_secs(1),
+            attr: file_attr(2),
+            generation: 0,
+        };
+
+        apply_attr_filter(&attr_filter, &mut entry.attr, &request);
+
+        let entry_out: fuse_entry_out = entry.into();
+
+        assert_eq!(entry_out.attr.uid, 65534);
+        assert_eq!(entry_out.attr.gid, 65534);
+        assert_eq!(entry_out.attr.ino, 2);
+
+        // readdirplus entry attr, the root request is not squashed
+        let mut attr = file_attr(3);
+
+        apply_attr_filter(&attr_filter, &mut attr, &Request { uid: 0, ..request });
+
+        assert_eq!(attr.uid, 1000);
+
+        // no filter
+        let mut attr = file_attr(3);
+
+        apply_attr_filter(&None, &mut attr, &request);
+
+        assert_eq!(attr, file_attr(3));
+    }
+
+    fn reply(unique: u64) -> Vec<u8> {
+        get_bincode_config()
+            .serialize(&fuse_out_header {
+                len: FUSE_OUT_HEADER_SIZE as u32,
+                error: 0,
+                unique,
+            })
+            .unwrap()
+    }
+
+    /// replies 1, 2 and 3 are queued, reply 2 fails with `errno`, return the written uniques.
+    fn write_replies_with_error(errno: i32) -> (IoResult<()>, Vec<u64>) {
+        let (sender, receiver) = unbounded();
+
+        for unique in 1..=3 {
+            sender.unbounded_send(reply(unique)).unwrap();
+        }
+
+        drop(sender);
+
+        let mut written = vec![];
+
+        let result = write_replies(receiver, |response| {
+            let unique = reply_unique(&response).unwrap();
+
+            let result = if unique == 2 {
+                Err(IoError::from_raw_os_error(errno))
+            } else {
+                written.push(unique);
+
+                Ok(())
+            };
+
+            futures_util::future::ready(result)
+        })
+        .now_or_never()
+        .unwrap();
+
+        (result, written)
+    }
+
+    #[test]
+    fn reply_cancelled_unique_is_dropped() {
+        // kernel can't find the request unique because it is interrupted
+        let (result, written) = write_replies_with_error(libc::ENOENT);
+
+        assert!(result.is_ok());
+        assert_eq!(written, [1, 3]);
+
+        let (result, written) = write_replies_with_error(libc::EINVAL);
+
+        assert!(result.is_ok());
+        assert_eq!(written, [1, 3]);
+    }
+
+    #[test]
+    fn reply_error_terminates_session() {
+        for errno in [libc::ENODEV, libc::EIO] {
+            let (result, written) = write_replies_with_error(errno);
+
+            assert_eq!(result.unwrap_err().raw_os_error(), Some(errno));
+            assert_eq!(written, [1]);
+        }
+    }
+
+    #[test]
+    fn slow_op_threshold() {
+        let slow_op = SlowOp {
+            threshold: Duration::from_millis(10),
+            opcode: fuse_opcode::FUSE_GETATTR,
+            inode: 2,
+            uid: 1000,
+            start: Instant::now(),
+        };
+
+        assert!(!slow_op.check_elapsed(Duration::from_millis(9)));
+        assert!(!slow_op.check_elapsed(Duration::from_millis(10)));
+        assert!(slow_op.check_elapsed(Duration::from_millis(11)));
+    }
+
+    #[cfg(feature = "tokio-runtime")]
+    mod handler {
+        use std::ffi::OsStr;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        use async_trait::async_trait;
+        use futures_util::stream::Empty;
+        use tracing::span;
+
+        use super::*;
+        use crate::raw::reply::*;
+
+        /// count the warning events, the handler tasks run on the test thread.
+        struct WarnCounter(Arc<AtomicUsize>);
+
+        impl tracing::Subscriber for WarnCounter {
+            fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+
+            fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+            fn event(&self, event: &tracing::Event<'_>) {
+                if *event.metadata().level() == tracing::Level::WARN {
+                    self.0.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+
+            fn enter(&self, _span: &span::Id) {}
+
+            fn exit(&self, _span: &span::Id) {}
+        }
+
+        #[derive(Debug, Eq, PartialEq)]
+        struct WriteCall {
+            inode: Inode,
+            fh: u64,
+            offset: u64,
+            data: Vec<u8>,
+            flags: u32,
+        }
+
+        #[derive(Default)]
+        struct StubFs {
+            getattr_delay: Duration,
+            writes: Mutex<Vec<WriteCall>>,
+            poll_handles: Mutex<Vec<Option<u64>>>,
+        }
+
+        #[async_trait]
+        impl Filesystem for StubFs {
+            type DirEntryStream = Empty<crate::Result<DirectoryEntry>>;
+            type DirEntryPlusStream = Empty<crate::Result<DirectoryEntryPlus>>;
+
+            async fn init(&self, _req: Request) -> crate::Result<()> {
+                Ok(())
+            }
+
+            async fn destroy(&self, _req: Request) {}
+
+            async fn lookup(
+                &self,
+                _req: Request,
+                _parent: Inode,
+                _name: &OsStr,
+            ) -> crate::Result<ReplyEntry> {
+                Ok(ReplyEntry {
+                    ttl: Duration::from_secs(1),
+                    attr: file_attr(3),
+                    generation: 7,
+                })
+            }
+
+            async fn getattr(
+                &self,
+                _req: Request,
+                inode: Inode,
+                _fh: Option<u64>,
+                _flags: u32,
+            ) -> crate::Result<ReplyAttr> {
+                // block the runtime thread so the operation is surely slow
+                std::thread::sleep(self.getattr_delay);
+
+                Ok(ReplyAttr {
+                    ttl: Duration::from_secs(1),
+                    attr: file_attr(inode),
+                })
+            }
+
+            async fn write(
+                &self,
+                _req: Request,
+                inode: Inode,
+                fh: u64,
+                offset: u64,
+                data: &[u8],
+                flags: u32,
+            ) -> crate::Result<ReplyWrite> {
+                self.writes.lock().unwrap().push(WriteCall {
+                    inode,
+                    fh,
+                    offset,
+                    data: data.to_vec(),
+                    flags,
+                });
+
+                Ok(ReplyWrite {
+                    written: data.len() as u64,
+                })
+            }
+
+            async fn release(
+                &self,
+                _req: Request,
+                _inode: Inode,
+                _fh: u64,
+                _flags: u32,
+                _lock_owner: u64,
+                _flush: bool,
+            ) -> crate::Result<()> {
+                Ok(())
+            }
+
+            async fn poll(
+                &self,
+                _req: Request,
+                _inode: Inode,
+                _fh: u64,
+                kh: Option<u64>,
+                _flags: u32,
+                _events: u32,
+                _notify: &Notify,
+            ) -> crate::Result<ReplyPoll> {
+                self.poll_handles.lock().unwrap().push(kh);
+
+                Ok(ReplyPoll { revents: 0 })
+            }
+        }
+
+        fn request(unique: u64) -> Request {
+            Request {
+                unique,
+                uid: 1000,
+                gid: 1000,
+                pid: 1,
+            }
+        }
+
+        fn in_header(
+            opcode: fuse_opcode,
+            request: Request,
+            nodeid: Inode,
+            body: &[u8],
+        ) -> fuse_in_header {
+            fuse_in_header {
+                len: (FUSE_IN_HEADER_SIZE + body.len()) as u32,
+                opcode: opcode as u32,
+                unique: request.unique,
+                nodeid,
+                uid: request.uid,
+                gid: request.gid,
+                pid: request.pid,
+                padding: 0,
+            }
+        }
+
+        fn serialize<T: serde::Serialize>(value: &T) -> Vec<u8> {
+            get_bincode_config().serialize(value).unwrap()
+        }
+
+        /// wait for the next reply, then let the handler task finish after it sends the reply.
+        async fn next_reply(session: &mut Session<StubFs>) -> (fuse_out_header, Vec<u8>) {
+            let reply = session
+                .response_receiver
+                .as_mut()
+                .unwrap()
+                .next()
+                .await
+                .unwrap();
+
+            tokio::task::yield_now().await;
+
+            let out_header = get_bincode_config()
+                .deserialize::<fuse_out_header>(&reply)
+                .unwrap();
+
+            assert_eq!(out_header.len as usize, reply.len());
+
+            (out_header, reply[FUSE_OUT_HEADER_SIZE..].to_vec())
+        }
+
+        fn slow_op(threshold: Duration) -> Option<SlowOp> {
+            Some(SlowOp {
+                threshold,
+                opcode: fuse_opcode::FUSE_GETATTR,
+                inode: 2,
+                uid: 1000,
+                start: Instant::now(),
+            })
+        }
+
+        #[tokio::test]
+        async fn slow_getattr_is_logged() {
+            let warns = Arc::new(AtomicUsize::new(0));
+            let _guard = tracing::subscriber::set_default(WarnCounter(warns.clone()));
+
+            let mut session = Session::new(MountOptions::default());
+            let fs = Arc::new(StubFs {
+                getattr_delay: Duration::from_millis(20),
+                ..Default::default()
+            });
+
+            let body = serialize(&fuse_getattr_in {
+                getattr_flags: 0,
+                dummy: 0,
+                fh: 0,
+            });
+
+            for (unique, slow_op, expect_warns) in [
+                (1, slow_op(Duration::from_millis(1)), 1),
+                (2, slow_op(Duration::from_secs(60)), 1),
+                (3, None, 1),
+            ] {
+                let request = request(unique);
+                let in_header = in_header(fuse_opcode::FUSE_GETATTR, request, 2, &body);
+
+                session
+                    .handle_getattr(request, slow_op, in_header, &body, &fs)
+                    .await;
+
+                let (out_header, _) = next_reply(&mut session).await;
+
+                assert_eq!(out_header.unique, unique);
+                assert_eq!(out_header.error, 0);
+                assert_eq!(warns.load(Ordering::SeqCst), expect_warns);
+            }
+        }
+
+        #[tokio::test]
+        async fn write_append_passes_kernel_offset_and_fh() {
+            let mut session = Session::new(MountOptions::default());
+            let fs = Arc::new(StubFs::default());
+
+            // the kernel already computed the end of file offset for the O_APPEND write
+            let flags = (libc::O_WRONLY | libc::O_APPEND) as u32;
+            let body = write_body(42, 4096, flags, b"hello");
+            let request = request(1);
+            let in_header = in_header(fuse_opcode::FUSE_WRITE, request, 2, &body);
+
+            session
+                .handle_write(request, None, in_header, &body, &fs)
+                .await;
+
+            let (out_header, reply) = next_reply(&mut session).await;
+
+            assert_eq!(out_header.unique, 1);
+            assert_eq!(out_header.error, 0);
+
+            let write_out = get_bincode_config()
+                .deserialize::<fuse_write_out>(&reply)
+                .unwrap();
+
+            assert_eq!(write_out.size, 5);
+
+            assert_eq!(
+                *fs.writes.lock().unwrap(),
+                [WriteCall {
+                    inode: 2,
+                    fh: 42,
+                    offset: 4096,
+                    data: b"hello".to_vec(),
+                    flags,
+                }]
+            );
+        }
+
+        #[tokio::test]
+        async fn attr_filter_applied_to_getattr_reply() {
+            let mut session = Session::new(MountOptions::default()).with_attr_filter(
+                |attr: &mut FileAttr, request: &Request| {
+                    if request.uid != 0 {
+                        attr.uid = 65534;
+                        attr.gid = 65534;
+                    }
+                },
+            );
+            let fs = Arc::new(StubFs::default());
+
+            let body = serialize(&fuse_getattr_in {
+                getattr_flags: 0,
+                dummy: 0,
+                fh: 0,
+            });
+            let request = request(1);
+            let in_header = in_header(fuse_opcode::FUSE_GETATTR, request, 2, &body);
+
+            session
+                .handle_getattr(request, None, in_header, &body, &fs)
+                .await;
+
+            let (out_header, reply) = next_reply(&mut session).await;
+
+            assert_eq!(out_header.error, 0);
+
+            let attr_out = get_bincode_config()
+                .deserialize::<fuse_attr_out>(&reply)
+                .unwrap();
+
+            assert_eq!(attr_out.attr.ino, 2);
+            assert_eq!(attr_out.attr.uid, 65534);
+            assert_eq!(attr_out.attr.gid, 65534);
+        }
+
+        #[tokio::test]
+        async fn path_attr_filter_applied_to_lookup_reply() {
+            let mut session = crate::path::Session::new(MountOptions::default())
+                .with_attr_filter(|attr: &mut crate::path::reply::FileAttr, _request| {
+                    attr.uid = 65534;
+                    attr.perm = 0o600;
+                })
+                .raw_session::<StubFs>();
+            let fs = Arc::new(StubFs::default());
+
+            let body = b"file\0";
+            let request = request(1);
+            let in_header = in_header(fuse_opcode::FUSE_LOOKUP, request, 1, body);
+
+            session
+                .handle_lookup(request, None, in_header, body, &fs)
+                .await;
+
+            let (out_header, reply) = next_reply(&mut session).await;
+
+            assert_eq!(out_header.error, 0);
+
+            let entry_out = get_bincode_config()
+                .deserialize::<fuse_entry_out>(&reply)
+                .unwrap();
+
+            // the inode and generation are kept
+            assert_eq!(entry_out.nodeid, 3);
+            assert_eq!(entry_out.generation, 7);
+            assert_eq!(entry_out.attr.ino, 3);
+            assert_eq!(entry_out.attr.uid, 65534);
+            assert_eq!(entry_out.attr.gid, 1000);
+            assert_eq!(entry_out.attr.mode & 0o7777, 0o600);
+        }
+
+        #[tokio::test]
+        async fn wakeup_released_poll_handle() {
+            let mut session = Session::new(MountOptions::default());
+            let fs = Arc::new(StubFs::default());
+
+            let body = serialize(&fuse_poll_in {
+                fh: 3,
+                kh: 10,
+                flags: FUSE_POLL_SCHEDULE_NOTIFY,
+                events: libc::POLLIN as u32,
+            });
+            let poll_request = request(1);
+            let poll_header = in_header(fuse_opcode::FUSE_POLL, poll_request, 2, &body);
+
+            session
+                .handle_poll(poll_request, None, poll_header, &body, &fs)
+                .await;
+
+            let (out_header, reply) = next_reply(&mut session).await;
+
+            assert_eq!(out_header.error, 0);
+            assert_eq!(*fs.poll_handles.lock().unwrap(), [Some(10)]);
+
+            let poll_out = get_bincode_config()
+                .deserialize::<fuse_poll_out>(&reply)
+                .unwrap();
+
+            assert_eq!(poll_out.revents, 0);
+
+            // the poll handle is live until the file is released
+            session.get_notify().wakeup(10).await.unwrap();
+
+            let (out_header, _) = next_reply(&mut session).await;
+
+            assert_eq!(out_header.error, fuse_notify_code::FUSE_POLL as i32);
+            assert_eq!(out_header.unique, 0);
+
+            let body = serialize(&fuse_release_in {
+                fh: 3,
+                flags: 0,
+                release_flags: 0,
+                lock_owner: 0,
+            });
+            let release_request = request(2);
+            let release_header = in_header(fuse_opcode::FUSE_RELEASE, release_request, 2, &body);
+
+            session
+                .handle_release(release_request, None, release_header, &body, &fs)
+                .await;
+
+            let (out_header, _) = next_reply(&mut session).await;
+
+            assert_eq!(out_header.unique, 2);
+            assert_eq!(out_header.error, 0);
+
+            assert_eq!(
+                session.get_notify().wakeup(10).await,
+                Err(Errno::from(libc::ENOENT))
+            );
+        }
+    }
 }